@@ -0,0 +1,38 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use space_precedence_parser::{eval, parse};
+
+/// Long flat sum with uniform spacing, e.g. `1 + 2 + 3 + ...`
+fn flat(terms: usize) -> String {
+    (0..terms)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Long expression alternating between tight and loose operators, e.g. `1*2 + 3*4 + ...`
+fn mixed(terms: usize) -> String {
+    (0..terms)
+        .map(|i| format!("{i}*{i}-1"))
+        .collect::<Vec<_>>()
+        .join("  +  ")
+}
+
+/// Chain of right-associative powers, which recurses for every operator
+fn powers(terms: usize) -> String {
+    vec!["1"; terms].join(" ^ ")
+}
+
+fn bench(c: &mut Criterion) {
+    for (name, input) in [
+        ("flat_10000", flat(10_000)), 
+        ("mixed_2000", mixed(2_000)), 
+        ("powers_200", powers(200)), 
+    ] {
+        c.bench_function(&format!("parse_{name}"), |b| b.iter(|| parse(black_box(&input)).unwrap()));
+        let expr = parse(&input).unwrap();
+        c.bench_function(&format!("eval_{name}"), |b| b.iter(|| eval(black_box(&expr)).unwrap()));
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
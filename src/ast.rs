@@ -0,0 +1,1361 @@
+use alloc::{
+    boxed::Box, 
+    collections::BTreeSet, 
+    format, 
+    string::{String, ToString}, 
+    vec, 
+    vec::Vec, 
+};
+use core::{
+    fmt::{self, Display, Formatter}, 
+    hash::{Hash, Hasher}, 
+};
+use crate::parser::{algebraic_precedence, is_postfix, is_right_associative};
+
+/// The AST structure being parsed. Trees compare and hash structurally, with literals compared by the bit
+/// patterns of their values, see `Number`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ast {
+    /// Literal numerical value
+    Literal(Number), 
+    /// Named value that's resolved when evaluated
+    Variable(String), 
+    /// Unary operation, either prefix such as `-x` or postfix such as `x!`
+    Unary {
+        op: UnOp, 
+        operand: Box<Ast>, 
+    }, 
+    /// Binary operation
+    Binary {
+        op: BinOp, 
+        lhs: Box<Ast>, 
+        rhs: Box<Ast>, 
+        #[cfg_attr(feature = "serde", serde(default))]
+        parenthesized: Parenthesized, 
+    }, 
+    /// Function applied to a parenthesized, comma-separated list of arguments, e.g. `log(8, 2)`
+    Call(String, Vec<Ast>), 
+    /// Conditional `cond ? then : else`, which is the `then` branch if the condition is non-zero and the
+    /// `else` branch otherwise
+    Ternary(Box<Ast>, Box<Ast>, Box<Ast>), 
+    /// Statement `name = value` that binds the value to a variable for the statements following it. Its own
+    /// value is that of the expression
+    Assign(String, Box<Ast>), 
+    /// Statements separated by `;`, e.g. `x = 3; x + 1`, whose value is that of the last one
+    Seq(Vec<Ast>), 
+    /// Binding `let name = value in body`, whose value is that of the body with the value bound to the name.
+    /// Unlike an assignment, the binding is only visible within the body
+    Let {
+        name: String, 
+        value: Box<Ast>, 
+        body: Box<Ast>, 
+    }, 
+}
+
+/// Operators of binary operations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinOp {
+    /// Addition, `+`
+    Add, 
+    /// Subtraction, `-`
+    Sub, 
+    /// Multiplication, `*`
+    Mul, 
+    /// Division, `/`
+    Div, 
+    /// Floor division, `//`, which rounds the quotient towards negative infinity, e.g. `-7 // 2 = -4`
+    FloorDiv, 
+    /// Remainder, `%`
+    Rem, 
+    /// Exponentiation, `^`
+    Pow, 
+    /// Less than, `<`
+    Lt, 
+    /// Greater than, `>`
+    Gt, 
+    /// Less than or equal, `<=`
+    Le, 
+    /// Greater than or equal, `>=`
+    Ge, 
+    /// Bitwise and of integers, `&`
+    BitAnd, 
+    /// Bitwise or of integers, `|`
+    BitOr, 
+    /// Left shift of integers, `<<`
+    Shl, 
+    /// Arithmetic right shift of integers, `>>`
+    Shr, 
+}
+
+impl BinOp {
+    /// All binary operators
+    pub const ALL: &'static [BinOp] = &[
+        BinOp::Add, 
+        BinOp::Sub, 
+        BinOp::Mul, 
+        BinOp::Div, 
+        BinOp::FloorDiv, 
+        BinOp::Rem, 
+        BinOp::Pow, 
+        BinOp::Lt, 
+        BinOp::Gt, 
+        BinOp::Le, 
+        BinOp::Ge, 
+        BinOp::BitAnd, 
+        BinOp::BitOr, 
+        BinOp::Shl, 
+        BinOp::Shr, 
+    ];
+
+    /// Symbol that the operator is written as
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+", 
+            BinOp::Sub => "-", 
+            BinOp::Mul => "*", 
+            BinOp::Div => "/", 
+            BinOp::FloorDiv => "//", 
+            BinOp::Rem => "%", 
+            BinOp::Pow => "^", 
+            BinOp::Lt => "<", 
+            BinOp::Gt => ">", 
+            BinOp::Le => "<=", 
+            BinOp::Ge => ">=", 
+            BinOp::BitAnd => "&", 
+            BinOp::BitOr => "|", 
+            BinOp::Shl => "<<", 
+            BinOp::Shr => ">>", 
+        }
+    }
+
+    /// Whether the operator is one of the comparisons `<`, `>`, `<=` and `>=`
+    pub fn is_comparison(self) -> bool {
+        matches!(self, BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge)
+    }
+
+    /// Operator that's written as the symbol, if any
+    pub fn from_symbol(symbol: &str) -> Option<BinOp> {
+        BinOp::ALL.iter()
+            .copied()
+            .find(|op| op.symbol() == symbol)
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Operators of unary operations
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnOp {
+    /// Negation, `-`
+    Neg, 
+    /// Unary plus, `+`, which leaves its operand unchanged
+    Plus, 
+    /// Factorial, the postfix `!`
+    Fact, 
+    /// Percentage, the postfix `%`, which divides by 100. See `ParseOptions::percent`
+    Percent, 
+    /// Bitwise not of an integer, `~`
+    Not, 
+    /// Square root, `sqrt`
+    Sqrt, 
+    /// Sine, `sin`
+    Sin, 
+    /// Cosine, `cos`
+    Cos, 
+    /// Tangent, `tan`
+    Tan, 
+    /// Natural logarithm, `ln`
+    Ln, 
+    /// Base 10 logarithm, `log`
+    Log, 
+    /// Absolute value, `abs`
+    Abs, 
+    /// Exponential function, `exp`
+    Exp, 
+    /// Function by any other name, e.g. one added to `ParseOptions::functions`
+    Function(String), 
+}
+
+impl UnOp {
+    /// All unary operators with a name of their own, i.e. other than `UnOp::Function`
+    pub const BUILTIN: &'static [UnOp] = &[
+        UnOp::Neg, 
+        UnOp::Plus, 
+        UnOp::Fact, 
+        UnOp::Percent, 
+        UnOp::Not, 
+        UnOp::Sqrt, 
+        UnOp::Sin, 
+        UnOp::Cos, 
+        UnOp::Tan, 
+        UnOp::Ln, 
+        UnOp::Log, 
+        UnOp::Abs, 
+        UnOp::Exp, 
+    ];
+
+    /// Symbol or function name that the operator is written as
+    pub fn name(&self) -> &str {
+        match self {
+            UnOp::Neg => "-", 
+            UnOp::Plus => "+", 
+            UnOp::Fact => "!", 
+            UnOp::Percent => "%", 
+            UnOp::Not => "~", 
+            UnOp::Sqrt => "sqrt", 
+            UnOp::Sin => "sin", 
+            UnOp::Cos => "cos", 
+            UnOp::Tan => "tan", 
+            UnOp::Ln => "ln", 
+            UnOp::Log => "log", 
+            UnOp::Abs => "abs", 
+            UnOp::Exp => "exp", 
+            UnOp::Function(name) => name, 
+        }
+    }
+
+    /// Operator that's written as the name, which is a `UnOp::Function` unless it's a builtin
+    pub fn from_name(name: &str) -> UnOp {
+        UnOp::BUILTIN.iter()
+            .find(|op| op.name() == name)
+            .cloned()
+            .unwrap_or_else(|| UnOp::Function(name.into()))
+    }
+}
+
+impl Display for UnOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Whether a binary operation was written in parentheses, e.g. `(1 + 2)`, which `Ast::to_canonical` keeps
+/// where they change the meaning. Like the lexeme of a literal, it only records how the source was written,
+/// so it's ignored when comparing and hashing trees
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parenthesized(pub bool);
+
+impl PartialEq for Parenthesized {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Parenthesized {}
+
+impl Hash for Parenthesized {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// Value of a literal, along with its kind and the lexeme it was parsed from. Numbers compare and hash by
+/// value and kind, ignoring the lexeme, such that e.g. `1.20` equals `1.2` but `2` doesn't equal `2.0`.
+/// Values are compared by their bit patterns rather than as floats, which makes the equality reflexive so
+/// numbers can be `Eq` and hashed: NaN equals itself, and `0.0` doesn't equal `-0.0`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Number {
+    pub value: f64, 
+    /// Whether the literal is an integer, e.g. `2`, or a float, e.g. `2.0`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: NumberKind, 
+    /// Source text of the literal, or `None` if the number wasn't parsed, e.g. when it's the result of
+    /// constant folding
+    pub lexeme: Option<String>, 
+}
+
+/// Kind of a literal. Parsed literals are floats if they're written with a decimal separator or an exponent,
+/// e.g. `2.0` or `2e3`, and integers otherwise, e.g. `2` or `0xff`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NumberKind {
+    /// Integer, which is displayed without a decimal point
+    Int, 
+    /// Float, which is displayed with a decimal point even if its value is integral, e.g. `2.0`
+    #[default]
+    Float, 
+}
+
+impl Display for NumberKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            NumberKind::Int => write!(f, "int"), 
+            NumberKind::Float => write!(f, "float"), 
+        }
+    }
+}
+
+/// Numbers created from a value are integers if the value is, e.g. `2.0` is the integer `2`
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        let kind = match value.is_finite() && value % 1.0 == 0.0 {
+            true => NumberKind::Int, 
+            false => NumberKind::Float, 
+        };
+        Number {
+            value, 
+            kind, 
+            lexeme: None, 
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits() && self.kind == other.kind
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.to_bits().hash(state);
+        self.kind.hash(state);
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.kind {
+            NumberKind::Float if self.value.is_finite() && self.value % 1.0 == 0.0 => {
+                write!(f, "{}.0", self.value)
+            }
+            _ => write!(f, "{}", self.value), 
+        }
+    }
+}
+
+impl Ast {
+    /// Creates a literal without a lexeme
+    pub fn literal(value: f64) -> Self {
+        Ast::Literal(value.into())
+    }
+
+    /// Creates a variable of the given name
+    pub fn variable(name: impl Into<String>) -> Self {
+        Ast::Variable(name.into())
+    }
+
+    /// Creates a unary operation, boxing the operand
+    pub fn unary(op: UnOp, operand: Ast) -> Self {
+        Ast::Unary {
+            op, 
+            operand: Box::new(operand), 
+        }
+    }
+
+    /// Creates a binary operation, boxing the operands
+    pub fn binary(op: BinOp, lhs: Ast, rhs: Ast) -> Self {
+        Ast::Binary {
+            op, 
+            lhs: Box::new(lhs), 
+            rhs: Box::new(rhs), 
+            parenthesized: Parenthesized::default(), 
+        }
+    }
+
+    /// Creates a conditional, boxing the branches
+    pub fn ternary(cond: Ast, then: Ast, otherwise: Ast) -> Self {
+        Ast::Ternary(Box::new(cond), Box::new(then), Box::new(otherwise))
+    }
+
+    /// Creates an assignment, boxing the value
+    pub fn assign(name: impl Into<String>, value: Ast) -> Self {
+        Ast::Assign(name.into(), Box::new(value))
+    }
+
+    /// Creates a binding, boxing the value and body
+    pub fn binding(name: impl Into<String>, value: Ast, body: Ast) -> Self {
+        Ast::Let {
+            name: name.into(), 
+            value: Box::new(value), 
+            body: Box::new(body), 
+        }
+    }
+
+    /// Renders the AST in infix notation with only the parentheses needed under regular algebraic precedence
+    /// and associativity, e.g. `1 * 3 + 4` rather than `((1 * 3) + 4)`. Since all operators are spaced
+    /// equally, the result parses back into the same AST, except for negative literals such as those made by
+    /// `Ast::map_literals`, which parse back as negations
+    pub fn to_infix(&self) -> String {
+        self.infix(Style::default())
+    }
+
+    /// Renders the AST like `Ast::to_infix`, but keeps the way that the source grouped its operations.
+    /// Parentheses that were written are kept where they change the meaning, e.g. in `(1 + 2) * 3`, and
+    /// dropped where they don't, e.g. `((1 + 2))` is rendered as `1 + 2`. Groupings that were written by
+    /// spacing are kept as such, so `1+2 * 3` is rendered as is rather than as `(1 + 2) * 3`. Like with
+    /// `Ast::to_infix`, the result parses back into the same AST except for negative literals
+    pub fn to_canonical(&self) -> String {
+        self.infix(Style { canonical: true, ..Style::default() })
+    }
+
+    /// Renders the AST like `Ast::to_infix`, but with literals written as they were in the source, e.g.
+    /// `1.20` rather than `1.2`. Note that the original spacing isn't retained; grouping is instead expressed
+    /// with the minimal parentheses
+    pub fn to_source(&self) -> String {
+        self.infix(Style { source: true, ..Style::default() })
+    }
+
+    /// Renders the AST in infix notation in the given style
+    fn infix(&self, style: Style) -> String {
+        match self {
+            Ast::Literal(number) => match (style.source, &number.lexeme) {
+                (true, Some(lexeme)) => lexeme.clone(), 
+                _ => number.to_string(), 
+            }
+            Ast::Variable(name) => name.clone(), 
+            Ast::Unary { op, operand: x } if is_postfix(op) => {
+                let needs_parens = match &**x {
+                    Ast::Literal(number) => number.value.is_sign_negative(), 
+                    Ast::Variable(_) | Ast::Call(..) => false, 
+                    Ast::Unary { op: sub_op, .. } => !is_postfix(sub_op), 
+                    Ast::Binary { .. }
+                    | Ast::Ternary(..)
+                    | Ast::Assign(..)
+                    | Ast::Seq(_)
+                    | Ast::Let { .. } => true, 
+                };
+                let x = x.infix(style);
+                if needs_parens {
+                    format!("({x}){op}")
+                } else {
+                    format!("{x}{op}")
+                }
+            }
+            Ast::Unary { op, operand: x } => {
+                let x = match **x {
+                    Ast::Binary { .. }
+                    | Ast::Ternary(..)
+                    | Ast::Assign(..)
+                    | Ast::Seq(_)
+                    | Ast::Let { .. } => {
+                        format!("({})", x.infix(style))
+                    }
+                    _ => x.infix(style), 
+                };
+                match op {
+                    UnOp::Neg | UnOp::Plus | UnOp::Not => format!("{op}{x}"), 
+                    _ => format!("{op} {x}"), 
+                }
+            }
+            Ast::Binary { .. } if style.canonical => self.infix_spaced(self.canonical_spacing(false), style), 
+            Ast::Binary { .. } => self.infix_spaced(1, style), 
+            Ast::Call(name, args) => {
+                let args: Vec<_> = args.iter().map(|arg| arg.infix(style)).collect();
+                format!("{name}({})", args.join(", "))
+            }
+            // the conditional groups from the right, so only a conditional as the condition is parenthesized
+            // in canonical form, operations that are spaced wider than the conditional are parenthesized
+            Ast::Ternary(cond, then, otherwise) => {
+                let spaced = |x: &Ast| style.canonical && x.canonical_spacing(false) > 1;
+                let cond = match **cond {
+                    Ast::Ternary(..) | Ast::Let { .. } => format!("({})", cond.infix(style)), 
+                    _ if spaced(cond) => format!("({})", cond.infix(style)), 
+                    _ => cond.infix(style), 
+                };
+                let otherwise = match spaced(otherwise) {
+                    true => format!("({})", otherwise.infix(style)), 
+                    false => otherwise.infix(style), 
+                };
+                format!("{cond} ? {} : {otherwise}", then.infix(style))
+            }
+            Ast::Assign(name, value) => format!("{name} = {}", value.infix(style)), 
+            Ast::Seq(statements) => statements.iter()
+                .map(|statement| statement.infix(style))
+                .collect::<Vec<_>>()
+                .join("; "), 
+            // the body extends as far as possible, so a binding is parenthesized wherever it's an operand
+            Ast::Let { name, value, body } => {
+                format!("let {name} = {} in {}", value.infix(style), body.infix(style))
+            }
+        }
+    }
+
+    /// Renders a binary operation with the given number of spaces around its operator. Operands that bind
+    /// looser are parenthesized, except in canonical form if they were grouped by spacing in the source, in
+    /// which case they're spaced narrower than the operator instead
+    fn infix_spaced(&self, spacing: usize, style: Style) -> String {
+        let Ast::Binary { op, lhs, rhs, .. } = self else {
+            return self.infix(style)
+        };
+        let spaced = |x: &Ast| matches!(x, Ast::Binary { parenthesized, .. } if !parenthesized.0);
+        let wrap = |x: &Ast, is_lhs: bool| match x {
+            _ if style.canonical && spaced(x) && groups(*op, x, is_lhs) => {
+                x.infix_spaced(x.canonical_spacing(true), style)
+            }
+            _ if groups(*op, x, is_lhs) => format!("({})", x.infix(style)), 
+            _ => x.infix(style), 
+        };
+        let space = " ".repeat(spacing);
+        format!("{}{space}{op}{space}{}", wrap(lhs, true), wrap(rhs, false))
+    }
+
+    /// Spacing of a binary operation in canonical form. It's wider than that of any operand that's grouped by
+    /// spacing, and at least that of the other operands, such that the operation reads the same. A single
+    /// space is used where possible, or none if the operation is itself grouped by spacing
+    fn canonical_spacing(&self, grouped: bool) -> usize {
+        let Ast::Binary { op, lhs, rhs, .. } = self else {
+            return 0
+        };
+        let operand = |x: &Ast, is_lhs: bool| match x {
+            Ast::Binary { parenthesized, .. } if groups(*op, x, is_lhs) => match parenthesized.0 {
+                true => 0, 
+                false => x.canonical_spacing(true) + 1, 
+            }
+            Ast::Binary { .. } => x.canonical_spacing(false), 
+            // a named operator would take the operation as its argument if it were spaced narrower
+            _ if x.has_named_prefix() => 1, 
+            _ => 0, 
+        };
+        let spacing = if grouped { 0 } else { 1 };
+        spacing.max(operand(lhs, true)).max(operand(rhs, false))
+    }
+
+    /// Whether the AST is a prefix operation that's written with a space after a named operator, e.g.
+    /// `sqrt x` or `-sqrt x`
+    fn has_named_prefix(&self) -> bool {
+        match self {
+            Ast::Unary { op, .. } if is_postfix(op) => false, 
+            Ast::Unary { op: UnOp::Neg | UnOp::Plus | UnOp::Not, operand } => operand.has_named_prefix(), 
+            Ast::Unary { .. } => true, 
+            _ => false, 
+        }
+    }
+
+    /// Renders the AST in reverse Polish notation, e.g. `1 2 3 * +` for `1 + 2 * 3`. Unary minus and plus are
+    /// emitted as `neg` and `pos` to distinguish them from their binary counterparts
+    pub fn to_rpn(&self) -> String {
+        match self {
+            Ast::Literal(number) => number.to_string(), 
+            Ast::Variable(name) => name.clone(), 
+            Ast::Unary { op, operand } => {
+                let op = match op {
+                    UnOp::Neg => "neg", 
+                    UnOp::Plus => "pos", 
+                    _ => op.name(), 
+                };
+                format!("{} {op}", operand.to_rpn())
+            }
+            Ast::Binary { op, lhs, rhs, .. } => format!("{} {} {op}", lhs.to_rpn(), rhs.to_rpn()), 
+            Ast::Call(name, args) => args.iter()
+                .map(Ast::to_rpn)
+                .chain([name.clone()])
+                .collect::<Vec<_>>()
+                .join(" "), 
+            Ast::Ternary(cond, then, otherwise) => {
+                format!("{} {} {} ?:", cond.to_rpn(), then.to_rpn(), otherwise.to_rpn())
+            }
+            Ast::Assign(name, value) => format!("{name} {} =", value.to_rpn()), 
+            Ast::Seq(statements) => statements.iter()
+                .map(Ast::to_rpn)
+                .collect::<Vec<_>>()
+                .join(" ; "), 
+            Ast::Let { name, value, body } => format!("{name} {} {} let", value.to_rpn(), body.to_rpn()), 
+        }
+    }
+
+    /// Renders the AST as a JSON object, e.g. `{"type":"binary","op":"+","lhs":...,"rhs":...}`. Literals are
+    /// written as `{"type":"literal","value":1.2}`, with `null` in place of non-finite values since JSON has
+    /// no representation for them
+    pub fn to_json(&self) -> String {
+        match self {
+            Ast::Literal(number) if number.value.is_finite() => {
+                format!(r#"{{"type":"literal","value":{}}}"#, number.value)
+            }
+            Ast::Literal(_) => r#"{"type":"literal","value":null}"#.into(), 
+            Ast::Variable(name) => format!(r#"{{"type":"variable","name":{}}}"#, json_string(name)), 
+            Ast::Unary { op, operand } => {
+                format!(r#"{{"type":"unary","op":{},"arg":{}}}"#, json_string(op.name()), operand.to_json())
+            }
+            Ast::Binary { op, lhs, rhs, .. } => format!(
+                r#"{{"type":"binary","op":{},"lhs":{},"rhs":{}}}"#, 
+                json_string(op.symbol()), 
+                lhs.to_json(), 
+                rhs.to_json(), 
+            ), 
+            Ast::Call(name, args) => {
+                let args: Vec<_> = args.iter().map(Ast::to_json).collect();
+                format!(r#"{{"type":"call","name":{},"args":[{}]}}"#, json_string(name), args.join(","))
+            }
+            Ast::Ternary(cond, then, otherwise) => format!(
+                r#"{{"type":"ternary","cond":{},"then":{},"else":{}}}"#, 
+                cond.to_json(), 
+                then.to_json(), 
+                otherwise.to_json(), 
+            ), 
+            Ast::Assign(name, value) => {
+                format!(r#"{{"type":"assign","name":{},"value":{}}}"#, json_string(name), value.to_json())
+            }
+            Ast::Seq(statements) => {
+                let statements: Vec<_> = statements.iter().map(Ast::to_json).collect();
+                format!(r#"{{"type":"seq","statements":[{}]}}"#, statements.join(","))
+            }
+            Ast::Let { name, value, body } => format!(
+                r#"{{"type":"let","name":{},"value":{},"body":{}}}"#, 
+                json_string(name), 
+                value.to_json(), 
+                body.to_json(), 
+            ), 
+        }
+    }
+
+    /// Renders the AST as a tree with one node per line and its operands indented below it, which is meant
+    /// for inspecting how an expression is grouped. Unary minus and plus are labeled `neg` and `pos` like in
+    /// `Ast::to_rpn`, calls are labeled by their name and conditionals by `?:`
+    ///
+    /// ```
+    /// # use space_precedence_parser::parse;
+    /// let tree = parse("1 * 2 - 3").unwrap().tree_string();
+    /// assert_eq!(tree, "-\n├─ *\n│  ├─ 1\n│  └─ 2\n└─ 3");
+    /// ```
+    pub fn tree_string(&self) -> String {
+        let mut lines = Vec::new();
+        self.tree_lines("", "", &mut lines);
+        lines.join("\n")
+    }
+
+    /// Label of the node itself without its operands, as shown by `Ast::tree_string`. Signs are labelled
+    /// `neg` and `pos` to tell them apart from subtractions and additions
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Ast::Literal(number) => number.to_string(), 
+            Ast::Variable(name) => name.clone(), 
+            Ast::Unary { op: UnOp::Neg, .. } => "neg".into(), 
+            Ast::Unary { op: UnOp::Plus, .. } => "pos".into(), 
+            Ast::Unary { op, .. } => op.name().into(), 
+            Ast::Binary { op, .. } => op.symbol().into(), 
+            Ast::Call(name, _) => name.clone(), 
+            Ast::Ternary(..) => "?:".into(), 
+            Ast::Assign(name, _) => format!("{name} ="), 
+            Ast::Seq(_) => ";".into(), 
+            Ast::Let { name, .. } => format!("let {name}"), 
+        }
+    }
+
+    /// Direct operands of the node from left to right
+    fn operands(&self) -> Vec<&Ast> {
+        match self {
+            Ast::Literal(_) | Ast::Variable(_) => Vec::new(), 
+            Ast::Unary { operand, .. } => vec![operand], 
+            Ast::Binary { lhs, rhs, .. } => vec![lhs, rhs], 
+            Ast::Call(_, args) => args.iter().collect(), 
+            Ast::Ternary(cond, then, otherwise) => vec![cond, then, otherwise], 
+            Ast::Assign(_, value) => vec![value], 
+            Ast::Seq(statements) => statements.iter().collect(), 
+            Ast::Let { value, body, .. } => vec![value, body], 
+        }
+    }
+
+    /// Appends the lines of the tree to `lines`, where the first line is prefixed by `head` and those of the
+    /// operands by `indent`
+    fn tree_lines(&self, head: &str, indent: &str, lines: &mut Vec<String>) {
+        let operands = self.operands();
+        lines.push(format!("{head}{}", self.label()));
+        for (i, operand) in operands.iter().enumerate() {
+            match i + 1 == operands.len() {
+                true => operand.tree_lines(&format!("{indent}└─ "), &format!("{indent}   "), lines), 
+                false => operand.tree_lines(&format!("{indent}├─ "), &format!("{indent}│  "), lines), 
+            }
+        }
+    }
+
+    /// Height of the tree, where a single literal or variable has a depth of 1
+    pub fn depth(&self) -> usize {
+        match self {
+            Ast::Literal(_) | Ast::Variable(_) => 1, 
+            Ast::Unary { operand, .. } => 1 + operand.depth(), 
+            Ast::Binary { lhs, rhs, .. } => 1 + lhs.depth().max(rhs.depth()), 
+            Ast::Call(_, args) => 1 + args.iter().map(Ast::depth).max().unwrap_or(0), 
+            Ast::Ternary(cond, then, otherwise) => {
+                1 + cond.depth().max(then.depth()).max(otherwise.depth())
+            }
+            Ast::Assign(_, value) => 1 + value.depth(), 
+            Ast::Seq(statements) => 1 + statements.iter().map(Ast::depth).max().unwrap_or(0), 
+            Ast::Let { value, body, .. } => 1 + value.depth().max(body.depth()), 
+        }
+    }
+
+    /// Total number of nodes in the tree, including the root
+    pub fn node_count(&self) -> usize {
+        match self {
+            Ast::Literal(_) | Ast::Variable(_) => 1, 
+            Ast::Unary { operand, .. } => 1 + operand.node_count(), 
+            Ast::Binary { lhs, rhs, .. } => 1 + lhs.node_count() + rhs.node_count(), 
+            Ast::Call(_, args) => 1 + args.iter().map(Ast::node_count).sum::<usize>(), 
+            Ast::Ternary(cond, then, otherwise) => {
+                1 + cond.node_count() + then.node_count() + otherwise.node_count()
+            }
+            Ast::Assign(_, value) => 1 + value.node_count(), 
+            Ast::Seq(statements) => 1 + statements.iter().map(Ast::node_count).sum::<usize>(), 
+            Ast::Let { value, body, .. } => 1 + value.node_count() + body.node_count(), 
+        }
+    }
+
+    /// Calls `f` on every node of the tree in pre-order, i.e. on each node before its children, with the
+    /// children visited from left to right
+    pub fn visit<F: FnMut(&Ast)>(&self, f: &mut F) {
+        f(self);
+        match self {
+            Ast::Literal(_) | Ast::Variable(_) => (), 
+            Ast::Unary { operand, .. } => operand.visit(f), 
+            Ast::Binary { lhs, rhs, .. } => {
+                lhs.visit(f);
+                rhs.visit(f);
+            }
+            Ast::Call(_, args) | Ast::Seq(args) => {
+                for arg in args {
+                    arg.visit(f);
+                }
+            }
+            Ast::Ternary(cond, then, otherwise) => {
+                cond.visit(f);
+                then.visit(f);
+                otherwise.visit(f);
+            }
+            Ast::Assign(_, value) => value.visit(f), 
+            Ast::Let { value, body, .. } => {
+                value.visit(f);
+                body.visit(f);
+            }
+        }
+    }
+
+    /// Rebuilds the tree with `f` applied to the value of every literal. The lexemes of the literals are
+    /// discarded, since they no longer match the values
+    pub fn map_literals<F: Fn(f64) -> f64>(self, f: F) -> Ast {
+        self.map_literals_by(&f)
+    }
+
+    /// Implementation of `Ast::map_literals` that borrows the function, such that recursing doesn't nest
+    /// references to it
+    fn map_literals_by<F: Fn(f64) -> f64>(self, f: &F) -> Ast {
+        match self {
+            Ast::Literal(number) => Ast::literal(f(number.value)), 
+            Ast::Variable(_) => self, 
+            Ast::Unary { op, operand } => Ast::unary(op, operand.map_literals_by(f)), 
+            Ast::Binary { op, lhs, rhs, .. } => {
+                Ast::binary(op, lhs.map_literals_by(f), rhs.map_literals_by(f))
+            }
+            Ast::Call(name, args) => {
+                Ast::Call(name, args.into_iter().map(|arg| arg.map_literals_by(f)).collect())
+            }
+            Ast::Ternary(cond, then, otherwise) => Ast::ternary(
+                cond.map_literals_by(f), 
+                then.map_literals_by(f), 
+                otherwise.map_literals_by(f), 
+            ), 
+            Ast::Assign(name, value) => Ast::assign(name, value.map_literals_by(f)), 
+            Ast::Seq(statements) => {
+                Ast::Seq(statements.into_iter().map(|statement| statement.map_literals_by(f)).collect())
+            }
+            Ast::Let { name, value, body } => {
+                Ast::binding(name, value.map_literals_by(f), body.map_literals_by(f))
+            }
+        }
+    }
+
+    /// Names of all variables referenced in the tree that aren't bound by it. A variable that's assigned by
+    /// a statement of a sequence is bound in the statements following it, e.g. `y` is free in
+    /// `x = y; x + 1` but `x` isn't. A binding `let x = ... in ...` binds its name only within its body
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        match self {
+            Ast::Variable(name) => BTreeSet::from([name.clone()]), 
+            Ast::Seq(statements) => {
+                let mut bound = BTreeSet::new();
+                let mut variables = BTreeSet::new();
+                for statement in statements {
+                    let free = statement.free_variables();
+                    variables.extend(free.into_iter().filter(|name| !bound.contains(name)));
+                    if let Ast::Assign(name, _) = statement {
+                        bound.insert(name.clone());
+                    }
+                }
+                variables
+            }
+            Ast::Let { name, value, body } => {
+                let mut variables = body.free_variables();
+                variables.remove(name);
+                variables.extend(value.free_variables());
+                variables
+            }
+            _ => self.operands().into_iter().flat_map(Ast::free_variables).collect(), 
+        }
+    }
+
+    /// Names of all operators used in the tree, as they're written, e.g. `+` or `sqrt`. Calls are included by
+    /// their function name, conditionals as `?:`, assignments as `=` and bindings as `let`. Note that
+    /// negation and subtraction are both `-`
+    pub fn operators(&self) -> BTreeSet<String> {
+        let mut operators = BTreeSet::new();
+        self.visit(&mut |node| {
+            let name = match node {
+                Ast::Literal(_) | Ast::Variable(_) | Ast::Seq(_) => return, 
+                Ast::Unary { op, .. } => op.name(), 
+                Ast::Binary { op, .. } => op.symbol(), 
+                Ast::Call(name, _) => name, 
+                Ast::Ternary(..) => "?:", 
+                Ast::Assign(..) => "=", 
+                Ast::Let { .. } => "let", 
+            };
+            operators.insert(name.into());
+        });
+        operators
+    }
+
+    /// Whether the tree references no variables, such that it can be evaluated without an environment. Note
+    /// that a constant tree may still fail to evaluate, e.g. `1 / 0`
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Ast::Literal(_) => true, 
+            Ast::Variable(_) => false, 
+            Ast::Unary { operand, .. } => operand.is_constant(), 
+            Ast::Binary { lhs, rhs, .. } => lhs.is_constant() && rhs.is_constant(), 
+            Ast::Call(_, args) => args.iter().all(Ast::is_constant), 
+            Ast::Ternary(cond, then, otherwise) => {
+                cond.is_constant() && then.is_constant() && otherwise.is_constant()
+            }
+            Ast::Assign(_, value) => value.is_constant(), 
+            Ast::Seq(_) | Ast::Let { .. } => self.free_variables().is_empty(), 
+        }
+    }
+
+    /// Whether the tree divides by a literal zero anywhere, with `/`, `//` or `%`, e.g. `x / 0`, including a
+    /// signed zero such as `x / -0`. This is a cheap check that doesn't evaluate anything, so divisors that
+    /// merely evaluate to zero such as `1 / (2 - 2)` aren't caught
+    pub fn has_literal_zero_division(&self) -> bool {
+        fn is_zero(ast: &Ast) -> bool {
+            match ast {
+                Ast::Literal(Number { value, .. }) => *value == 0.0, 
+                Ast::Unary { op: UnOp::Neg | UnOp::Plus, operand } => is_zero(operand), 
+                _ => false, 
+            }
+        }
+
+        let mut found = false;
+        self.visit(&mut |node| {
+            if let Ast::Binary { op: BinOp::Div | BinOp::FloorDiv | BinOp::Rem, rhs, .. } = node {
+                found |= is_zero(rhs);
+            }
+        });
+        found
+    }
+}
+
+/// Options for rendering an AST in infix notation
+#[derive(Clone, Copy, Default)]
+struct Style {
+    /// Whether literals are written with their source lexemes, see `Ast::to_source`
+    source: bool, 
+    /// Whether the grouping of the source is kept, see `Ast::to_canonical`
+    canonical: bool, 
+}
+
+/// Whether an operand of a binary operator must be grouped to be read as such in infix notation with equal
+/// spacing. Operands need grouping if they bind looser than the operator, or equally loose on the side
+/// opposite to the associativity. Comparisons of comparisons are grouped on either side, since they'd
+/// otherwise read as a chain. Prefix operations on the lhs of `^` are also grouped since e.g. `-2 ^ 2` reads
+/// as `-(2 ^ 2)`
+fn groups(op: BinOp, operand: &Ast, is_lhs: bool) -> bool {
+    match operand {
+        Ast::Binary { op: sub_op, .. } => {
+            let prec = algebraic_precedence(op);
+            let sub_prec = algebraic_precedence(*sub_op);
+            let chains = op.is_comparison() && sub_op.is_comparison();
+            sub_prec > prec || sub_prec == prec && (is_lhs == is_right_associative(op) || chains)
+        }
+        Ast::Unary { op: sub_op, .. } => is_lhs && op == BinOp::Pow && !is_postfix(sub_op), 
+        Ast::Literal(number) => is_lhs && op == BinOp::Pow && number.value.is_sign_negative(), 
+        Ast::Ternary(..) | Ast::Assign(..) | Ast::Seq(_) | Ast::Let { .. } => true, 
+        Ast::Variable(_) | Ast::Call(..) => false, 
+    }
+}
+
+/// Quotes a string for JSON, escaping quotes, backslashes and control characters
+fn json_string(string: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""), 
+            '\\' => quoted.push_str("\\\\"), 
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)), 
+            c => quoted.push(c), 
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Displays the AST fully parenthesized with spaces around operators, e.g. `(1 + (2 * 3))`. The alternate
+/// flag omits the spaces for compact output, e.g. `{:#}` displays `(1+(2*3))` and `max(1,2)`, except after
+/// named operators such as `sqrt`, which would otherwise run into their operand
+impl Display for Ast {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // sub-trees are written to the same formatter, such that they inherit its flags
+        let space = if f.alternate() { "" } else { " " };
+        match self {
+            Ast::Literal(number) => write!(f, "{number}"),
+            Ast::Variable(name) => write!(f, "{name}"), 
+            // a spaced `%` would be read as a modulo
+            Ast::Unary { op: UnOp::Percent, operand } => {
+                write!(f, "(")?;
+                operand.fmt(f)?;
+                write!(f, "%)")
+            }
+            Ast::Unary { op, operand } if is_postfix(op) => {
+                write!(f, "(")?;
+                operand.fmt(f)?;
+                write!(f, "{space}{op})")
+            }
+            Ast::Unary { op, operand } => {
+                let space = match op {
+                    UnOp::Neg | UnOp::Plus | UnOp::Not => space, 
+                    _ => " ", 
+                };
+                write!(f, "({op}{space}")?;
+                operand.fmt(f)?;
+                write!(f, ")")
+            }
+            Ast::Binary { op, lhs, rhs, .. } => {
+                write!(f, "(")?;
+                lhs.fmt(f)?;
+                write!(f, "{space}{op}{space}")?;
+                rhs.fmt(f)?;
+                write!(f, ")")
+            }
+            Ast::Call(name, args) => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",{space}")?;
+                    }
+                    arg.fmt(f)?;
+                }
+                write!(f, ")")
+            }
+            Ast::Ternary(cond, then, otherwise) => {
+                write!(f, "(")?;
+                cond.fmt(f)?;
+                write!(f, "{space}?{space}")?;
+                then.fmt(f)?;
+                write!(f, "{space}:{space}")?;
+                otherwise.fmt(f)?;
+                write!(f, ")")
+            }
+            Ast::Assign(name, value) => {
+                write!(f, "{name}{space}={space}")?;
+                value.fmt(f)
+            }
+            Ast::Seq(statements) => {
+                for (i, statement) in statements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";{space}")?;
+                    }
+                    statement.fmt(f)?;
+                }
+                Ok(())
+            }
+            // the keywords need spaces to be told apart from the name and operands
+            Ast::Let { name, value, body } => {
+                write!(f, "(let {name}{space}={space}")?;
+                value.fmt(f)?;
+                write!(f, " in ")?;
+                body.fmt(f)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn equality() {
+        let expected = Ast::Binary {
+            op: BinOp::Add, 
+            lhs: Box::new(Ast::Literal(1.0.into())), 
+            rhs: Box::new(Ast::Literal(2.0.into())), 
+            parenthesized: Parenthesized::default(), 
+        };
+        assert_eq!(parse("1+2").unwrap(), expected);
+        assert_eq!(parse("1 + 2").unwrap(), expected.clone());
+        assert_ne!(parse("2 + 1").unwrap(), expected);
+        assert_ne!(parse("1 - 2").unwrap(), expected);
+        assert_ne!(parse("0.1 + 0.2").unwrap(), parse("0.3").unwrap());
+        assert_ne!(parse("1.0 + 2").unwrap(), parse("1 + 2").unwrap());
+        assert_eq!(parse("1.50 + 2").unwrap(), parse("1.5 + 2").unwrap());
+    }
+
+    #[test]
+    fn hashing() {
+        use std::collections::HashSet;
+
+        let inputs = ["1 + 2*3", "1+2 * 3", "(1) + (2 * 3)", "1.0 + 2*3", "1 + 2 * 3", "x * y", "x*y", "y*x"];
+        let trees: HashSet<_> = inputs.iter().map(|input| parse(input).unwrap()).collect();
+        assert_eq!(trees.len(), 5);
+        assert!(trees.contains(&parse("1+(2*3)").unwrap()));
+        assert!(trees.contains(&parse("(1 + 2) * 3").unwrap()));
+        // literals of different kinds are distinct even if their values are equal
+        assert!(trees.contains(&parse("1.0 + (2*3)").unwrap()));
+        assert!(!trees.contains(&parse("1.0 + 2.0*3").unwrap()));
+
+        // literals are equal exactly if their bits are
+        let nan = Ast::literal(f64::NAN);
+        assert_eq!(nan, nan.clone());
+        assert_ne!(Ast::literal(0.0), Ast::literal(-0.0));
+        assert_eq!(HashSet::from([nan.clone(), nan, Ast::literal(0.0), Ast::literal(-0.0)]).len(), 3);
+    }
+
+    #[test]
+    fn constructors() {
+        let assert_constructed = |input: &str, expected: Ast| {
+            assert_eq!(parse(input).unwrap(), expected);
+        };
+        assert_constructed("1", Ast::literal(1.0));
+        assert_constructed("x", Ast::variable("x"));
+        assert_constructed("1 + 2", Ast::binary(BinOp::Add, Ast::literal(1.0), Ast::literal(2.0)));
+        assert_constructed("sqrt x!", Ast::unary(UnOp::Sqrt, Ast::unary(UnOp::Fact, Ast::variable("x"))));
+        assert_constructed("1 * 2+x", Ast::binary(
+            BinOp::Mul, 
+            Ast::literal(1.0), 
+            Ast::binary(BinOp::Add, Ast::literal(2.0), Ast::variable("x")), 
+        ));
+        let negated = Ast::unary(UnOp::Neg, Ast::literal(2.0));
+        assert_constructed("-2 ^ y", Ast::binary(BinOp::Pow, negated, Ast::variable("y")));
+    }
+
+    #[test]
+    fn op_names() {
+        for &op in BinOp::ALL {
+            assert_eq!(BinOp::from_symbol(op.symbol()), Some(op));
+        }
+        for op in UnOp::BUILTIN {
+            assert_eq!(&UnOp::from_name(op.name()), op);
+        }
+        assert_eq!(BinOp::from_symbol("?"), None);
+        assert_eq!(UnOp::from_name("neg"), UnOp::Function("neg".into()));
+        assert_eq!(format!("{} {}", BinOp::Pow, UnOp::Sqrt), "^ sqrt");
+    }
+
+    #[test]
+    fn display() {
+        let assert_display = |input: &str, spaced: &str, compact: &str| {
+            let expr = parse(input).unwrap();
+            assert_eq!(format!("{expr}"), spaced);
+            assert_eq!(format!("{expr:#}"), compact);
+            assert_eq!(parse(compact).unwrap(), expr);
+        };
+        assert_display("1 + 2", "(1 + 2)", "(1+2)");
+        assert_display("1 * 2+3", "(1 * (2 + 3))", "(1*(2+3))");
+        assert_display("-x + sqrt  4!", "((- x) + (sqrt (4 !)))", "((-x)+(sqrt (4!)))");
+        assert_display("max(1, 2) ? ~3 : 4", "(max(1, 2) ? (~ 3) : 4)", "(max(1,2)?(~3):4)");
+        assert_display("x = 1; x", "x = 1; x", "x=1;x");
+    }
+
+    #[test]
+    fn infix() {
+        let assert_infix = |input: &str, expected: &str| {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.to_infix(), expected);
+            assert_eq!(parse(&expr.to_infix()).unwrap(), expr);
+        };
+        assert_infix("1 * 3 + 4", "1 * 3 + 4");
+        assert_infix("1 * 2+3", "1 * (2 + 3)");
+        assert_infix("1*    3+4   -   5/6", "1 * (3 + 4 - 5 / 6)");
+        assert_infix("(2 - 3) - 4", "2 - 3 - 4");
+        assert_infix("2 - (3 - 4)", "2 - (3 - 4)");
+        assert_infix("2 ^ 3 ^ 2", "2 ^ 3 ^ 2");
+        assert_infix("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2");
+        assert_infix("- 2 ^ 2", "-(2 ^ 2)");
+        assert_infix("-2 ^ 2", "(-2) ^ 2");
+        assert_infix("sqrt  1 + 3", "sqrt (1 + 3)");
+        assert_infix("sqrt sqrt 1 + 3", "sqrt sqrt 1 + 3");
+        assert_infix("2 * -3", "2 * -3");
+        assert_infix("+2 - +3", "+2 - +3");
+        assert_infix("2 + 3!", "2 + 3!");
+        assert_infix("2+3 !", "(2 + 3)!");
+        assert_infix("(-3)!", "(-3)!");
+        assert_infix("-3!", "-3!");
+        assert_infix("a ? b : c ? d : f", "a ? b : c ? d : f");
+        assert_infix("(a ? b : c) ? d : f", "(a ? b : c) ? d : f");
+        assert_infix("a ? b : c  +  1", "(a ? b : c) + 1");
+        assert_infix("-(a ? b : c)", "-(a ? b : c)");
+        assert_infix("3! ^ 2", "3! ^ 2");
+        assert_infix("x * (y + z)", "x * (y + z)");
+        assert_infix("((1 + 2))", "1 + 2");
+        assert_infix("(1) + (2 * 3)", "1 + 2 * 3");
+        assert_infix("((1 + 2)) * (3)", "(1 + 2) * 3");
+        assert_infix("2 * max(1, 2+3)", "2 * max(1, 2 + 3)");
+        assert_infix("1 < 2  <  3", "(1 < 2) < 3");
+        assert_infix("1  <=  2 > 3", "1 <= (2 > 3)");
+        assert_infix("1 + 2 < 3", "1 + 2 < 3");
+    }
+
+    #[test]
+    fn canonical() {
+        // inputs that only differ in redundant parentheses render the same
+        let groups = [
+            &["1 + 2", "(1 + 2)", "((1+2))", "(1) + (2)"][..], 
+            &["(1 + 2) * 3", "((1 + 2)) * (3)", "((1+2)) * 3"], 
+            &["1 + 2 * 3", "1 + (2 * 3)", "(1 + 2*3)"], 
+            &["2 ^ 3 ^ 2", "2 ^ (3 ^ 2)"], 
+            &["-(2 ^ 2)", "- 2 ^ 2", "-(2^2)"], 
+        ];
+        for inputs in groups {
+            let canonical = parse(inputs[0]).unwrap().to_canonical();
+            for input in inputs {
+                assert_eq!(parse(input).unwrap().to_canonical(), canonical, "{input}");
+            }
+        }
+
+        let assert_canonical = |input: &str, expected: &str| {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.to_canonical(), expected);
+            assert_eq!(parse(expected).unwrap(), expr);
+        };
+        // groupings written by spacing are kept, unlike in `Ast::to_infix`
+        assert_canonical("1+2 * 3", "1+2 * 3");
+        assert_eq!(parse("1+2 * 3").unwrap().to_infix(), "(1 + 2) * 3");
+        assert_canonical("2 - 3-4", "2 - 3-4");
+        assert_canonical("2^3 ^ 2", "2^3 ^ 2");
+        assert_canonical("1 < 2  <  3", "1<2 < 3");
+        assert_canonical("(1 + 2) * 3+4", "(1 + 2) * 3+4");
+        assert_canonical("1 * 2+3  *  4", "1 * 2+3 * 4");
+        assert_canonical("1 * 2+3*4", "1  *  2 + 3 * 4");
+        assert_canonical("2 * sqrt 4+1", "2 * sqrt (4 + 1)");
+        assert_canonical("2 * (sqrt 4)+1", "2  *  sqrt 4 + 1");
+        assert_canonical("(a ? b : c) * 2+1", "(a ? b : c) * 2+1");
+        assert_canonical("a ? b : c * 1+2  +  3", "(a ? b : c * 1+2) + 3");
+        assert_canonical("a ? b : (c  *  1 + 2*3)", "a ? b : (c  *  1 + 2 * 3)");
+    }
+
+    #[test]
+    fn kinds() {
+        let kind = |input| match parse(input).unwrap() {
+            Ast::Literal(number) => number.kind, 
+            _ => panic!("Expected a literal"), 
+        };
+        assert_eq!(kind("2"), NumberKind::Int);
+        assert_eq!(kind("2.0"), NumberKind::Float);
+        assert_eq!(kind("2."), NumberKind::Float);
+        assert_eq!(kind("2e3"), NumberKind::Float);
+        assert_eq!(kind("0xe"), NumberKind::Int);
+        assert_eq!(kind("1_000"), NumberKind::Int);
+        assert_eq!(kind("pi"), NumberKind::Float);
+
+        assert_eq!(parse("2 + 3").unwrap().to_string(), "(2 + 3)");
+        assert_eq!(parse("2.0 + 3").unwrap().to_string(), "(2.0 + 3)");
+        assert_eq!(parse("2.50 + 1e20").unwrap().to_string(), "(2.5 + 100000000000000000000.0)");
+        assert_ne!(parse("2.0").unwrap(), parse("2").unwrap());
+        assert_eq!(parse("2e0").unwrap(), parse("2.0").unwrap());
+        assert_eq!(Ast::literal(2.0).to_string(), "2");
+        assert_eq!(Ast::literal(-0.5).to_string(), "-0.5");
+    }
+
+    #[test]
+    fn source() {
+        let assert_source = |input: &str, expected: &str| {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.to_source(), expected);
+            assert_eq!(parse(&expr.to_source()).unwrap(), expr);
+        };
+        assert_source("1.20 + 3", "1.20 + 3");
+        assert_source("1_000 * 2.5e-3+1", "1_000 * (2.5e-3 + 1)");
+        assert_source("sqrt 0.50", "sqrt 0.50");
+        assert_source("x = 1.0;y = x * 2+1", "x = 1.0; y = x * (2 + 1)");
+        assert_eq!(parse("1.20 + 3").unwrap().to_infix(), "1.2 + 3");
+    }
+
+    #[test]
+    fn rpn() {
+        let assert_rpn = |input: &str, expected: &str| {
+            assert_eq!(parse(input).unwrap().to_rpn(), expected);
+        };
+        assert_rpn("1", "1");
+        assert_rpn("1 + 2 * 3", "1 2 3 * +");
+        assert_rpn("1 * 2+3", "1 2 3 + *");
+        assert_rpn("1 - 2 - 3", "1 2 - 3 -");
+        assert_rpn("sqrt 1", "1 sqrt");
+        assert_rpn("-1", "1 neg");
+        assert_rpn("+1", "1 pos");
+        assert_rpn("1 + 3!", "1 3 ! +");
+        assert_rpn("sqrt  1 - -2", "1 2 neg - sqrt");
+        assert_rpn("2 ^ 3 ^ sin 2", "2 3 2 sin ^ ^");
+        assert_rpn("log(8, 1 + 1)", "8 1 1 + log");
+        assert_rpn("a ? b : c + 1", "a b c 1 + ?:");
+        assert_rpn("x = 1 + 2; x", "x 1 2 + = ; x");
+    }
+
+    #[test]
+    fn json() {
+        let assert_json = |input: &str, expected: &str| {
+            assert_eq!(parse(input).unwrap().to_json(), expected);
+        };
+        assert_json("1.2", r#"{"type":"literal","value":1.2}"#);
+        assert_json("1 + 2 * 3", concat!(
+            r#"{"type":"binary","op":"+","lhs":{"type":"literal","value":1},"rhs":"#, 
+            r#"{"type":"binary","op":"*","lhs":{"type":"literal","value":2},"#, 
+            r#""rhs":{"type":"literal","value":3}}}"#, 
+        ));
+        assert_json("-x", r#"{"type":"unary","op":"-","arg":{"type":"variable","name":"x"}}"#);
+        assert_json("max(x, 1)", concat!(
+            r#"{"type":"call","name":"max","args":"#, 
+            r#"[{"type":"variable","name":"x"},{"type":"literal","value":1}]}"#, 
+        ));
+        assert_json("a ? 1 : 2", concat!(
+            r#"{"type":"ternary","cond":{"type":"variable","name":"a"},"#, 
+            r#""then":{"type":"literal","value":1},"else":{"type":"literal","value":2}}"#, 
+        ));
+        assert_json("x = 1; x", concat!(
+            r#"{"type":"seq","statements":[{"type":"assign","name":"x","#, 
+            r#""value":{"type":"literal","value":1}},{"type":"variable","name":"x"}]}"#, 
+        ));
+        assert_json("1e999", r#"{"type":"literal","value":null}"#);
+        assert_eq!(json_string("a\"b\\c\n"), r#""a\"b\\c\u000a""#);
+    }
+
+    #[test]
+    fn tree_string() {
+        let tree = |input: &str| parse(input).unwrap().tree_string();
+        assert_eq!(tree("x"), "x");
+        assert_eq!(tree("-(1 + 2) * max(a, b!)  ?  3 : 4").split('\n').collect::<Vec<_>>(), [
+            "?:", 
+            "├─ *", 
+            "│  ├─ neg", 
+            "│  │  └─ +", 
+            "│  │     ├─ 1", 
+            "│  │     └─ 2", 
+            "│  └─ max", 
+            "│     ├─ a", 
+            "│     └─ !", 
+            "│        └─ b", 
+            "├─ 3", 
+            "└─ 4", 
+        ]);
+        assert_eq!(tree("x = 1; x"), ";\n├─ x =\n│  └─ 1\n└─ x");
+    }
+
+    #[test]
+    fn size() {
+        let assert_size = |input: &str, depth: usize, node_count: usize| {
+            let expr = parse(input).unwrap();
+            assert_eq!((expr.depth(), expr.node_count()), (depth, node_count));
+        };
+        assert_size("1", 1, 1);
+        assert_size("x", 1, 1);
+        assert_size("-1", 2, 2);
+        assert_size("1+2+3+4", 4, 7);
+        assert_size("1+2 * 3+4", 3, 7);
+        assert_size("sqrt 3! + 1", 4, 5);
+        assert_size("x = 1 + 2; x", 4, 6);
+
+        let binary = Ast::binary(BinOp::Add, Ast::unary(UnOp::Neg, Ast::literal(1.0)), Ast::literal(1.0));
+        assert_eq!((binary.depth(), binary.node_count()), (3, 4));
+    }
+
+    #[test]
+    fn visit() {
+        let expr = parse("1 + sqrt x * max(2, -3)").unwrap();
+        let mut literals = 0;
+        expr.visit(&mut |node| {
+            if let Ast::Literal(_) = node {
+                literals += 1;
+            }
+        });
+        assert_eq!(literals, 3);
+
+        let mut nodes = Vec::new();
+        parse("1 - 2 * x").unwrap().visit(&mut |node| nodes.push(node.to_infix()));
+        assert_eq!(nodes, ["1 - 2 * x", "1", "2 * x", "2", "x"]);
+    }
+
+    #[test]
+    fn map_literals() {
+        let doubled = parse("1 + 2 * 3").unwrap().map_literals(|x| x * 2.0);
+        assert_eq!(doubled.to_string(), "(2 + (4 * 6))");
+        assert_eq!(crate::eval(&doubled), Ok(26.0));
+
+        let expr = parse("1.50 * x - max(2, sqrt 4)!").unwrap().map_literals(|x| -x);
+        assert_eq!(expr.to_source(), "-1.5 * x - max(-2, sqrt -4)!");
+    }
+
+    #[test]
+    fn free_variables() {
+        let assert_variables = |input: &str, expected: &[&str]| {
+            let expr = parse(input).unwrap();
+            let variables: Vec<_> = expr.free_variables().into_iter().collect();
+            assert_eq!(variables, expected);
+            assert_eq!(expr.is_constant(), expected.is_empty());
+        };
+        assert_variables("x + 2", &["x"]);
+        assert_variables("x * y", &["x", "y"]);
+        assert_variables("1 + 2", &[]);
+        assert_variables("y * x + x", &["x", "y"]);
+        assert_variables("sqrt pi * -z!", &["z"]);
+        assert_variables("x = y; x + 1", &["y"]);
+        assert_variables("x = 1; y = x * 2; y", &[]);
+        assert_variables("x; x = 1; x", &["x"]);
+        assert_variables("x = x + 1", &["x"]);
+        assert_variables("let x = y in x * 2", &["y"]);
+        assert_variables("let x = x in x", &["x"]);
+        assert_variables("(let x = 1 in x) + x", &["x"]);
+    }
+
+    #[test]
+    fn operators() {
+        let assert_operators = |input: &str, expected: &[&str]| {
+            let operators: Vec<_> = parse(input).unwrap().operators().into_iter().collect();
+            assert_eq!(operators, expected);
+        };
+        assert_operators("1 + 2 / 3", &["+", "/"]);
+        assert_operators("sqrt 1", &["sqrt"]);
+        assert_operators("x", &[]);
+        assert_operators("1 - -2 - 3", &["-"]);
+        assert_operators("max(1, 3!) > 2 ? 1 : 0", &["!", ">", "?:", "max"]);
+        assert_operators("x = 1; x * 2", &["*", "="]);
+        assert!(!parse("1 * 2 + x").unwrap().operators().contains("/"));
+    }
+
+    #[test]
+    fn literal_zero_division() {
+        let divides_by_zero = |input| parse(input).unwrap().has_literal_zero_division();
+        assert!(divides_by_zero("1 / 0"));
+        assert!(divides_by_zero("x % 0.0"));
+        assert!(divides_by_zero("7 // 0"));
+        assert!(divides_by_zero("1 + sqrt(2 * (x / 0))"));
+        assert!(divides_by_zero("x = 1; let y = 2 in y / 0x0"));
+        assert!(divides_by_zero("1 / -0"));
+        assert!(divides_by_zero("1 % -0"));
+        assert!(divides_by_zero("x // +-0.0"));
+        assert!(!divides_by_zero("1 / (2 - 2)"));
+        assert!(!divides_by_zero("1 / 2"));
+        assert!(!divides_by_zero("0 / 1"));
+        assert!(!divides_by_zero("1 * 0"));
+        assert!(!divides_by_zero("1 / x"));
+        assert!(!divides_by_zero("1 / -x"));
+        assert!(!divides_by_zero("1 / -0!"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        for input in ["1.20 + 2 * 3", "sqrt  x - -2", "3! ^ 2", "(1 + 2)"] {
+            let expr = parse(input).unwrap();
+            let json = serde_json::to_string(&expr).unwrap();
+            assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), expr);
+        }
+        let expr: Ast = serde_json::from_str(r#"{"Literal":{"value":1.2,"lexeme":"1.20"}}"#).unwrap();
+        assert_eq!(expr.to_source(), "1.20");
+    }
+}
@@ -0,0 +1,113 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use crate::Ast;
+
+/// Describes the first structural difference between an expected AST and an actual one, or `None` if they're
+/// equal. The difference is annotated with the path to it from the root, e.g. `at lhs.rhs: expected + got *`.
+/// Literals are compared by value and kind, like `Ast`'s `PartialEq`
+///
+/// ```
+/// # use space_precedence_parser::{parse, structural_diff};
+/// let expected = parse("1 + 2 * 3").unwrap();
+/// assert_eq!(structural_diff(&expected, &parse("1 + 2*3").unwrap()), None);
+/// let diff = structural_diff(&expected, &parse("1 + 2 - 3").unwrap());
+/// assert_eq!(diff.as_deref(), Some("at root: expected + got -"));
+/// ```
+pub fn structural_diff(expected: &Ast, actual: &Ast) -> Option<String> {
+    diff(expected, actual, &mut Vec::new())
+}
+
+/// Implementation of `structural_diff`, where `path` holds the names of the operands leading to the nodes
+fn diff(expected: &Ast, actual: &Ast, path: &mut Vec<String>) -> Option<String> {
+    let at = |path: &[String]| match path.is_empty() {
+        true => "root".into(), 
+        false => path.join("."), 
+    };
+    // literals are equal regardless of their lexemes, e.g. `1.50` and `1.5`
+    if let (Ast::Literal(number), Ast::Literal(actual_number)) = (expected, actual) {
+        if number == actual_number {
+            return None
+        }
+    }
+    let (label, actual_label) = (expected.label(), actual.label());
+    if label != actual_label {
+        return Some(format!("at {}: expected {label} got {actual_label}", at(path)))
+    }
+    let (operands, actual_operands) = (named_operands(expected), named_operands(actual));
+    if operands.len() != actual_operands.len() {
+        let (len, actual_len) = (operands.len(), actual_operands.len());
+        return Some(format!("at {}: expected {len} operands of {label} got {actual_len}", at(path)))
+    }
+    for ((name, operand), (_, actual_operand)) in operands.into_iter().zip(actual_operands) {
+        path.push(name);
+        if let Some(diff) = diff(operand, actual_operand, path) {
+            return Some(diff)
+        }
+        path.pop();
+    }
+    None
+}
+
+/// Operands of the node along with their names in the path to a difference
+fn named_operands(ast: &Ast) -> Vec<(String, &Ast)> {
+    fn indexed<'a>(name: &str, operands: &'a [Ast]) -> Vec<(String, &'a Ast)> {
+        operands.iter()
+            .enumerate()
+            .map(|(i, operand)| (format!("{name}[{i}]"), operand))
+            .collect()
+    }
+    match ast {
+        Ast::Literal(_) | Ast::Variable(_) => Vec::new(), 
+        Ast::Unary { operand, .. } => vec![("operand".into(), operand)], 
+        Ast::Binary { lhs, rhs, .. } => vec![("lhs".into(), lhs), ("rhs".into(), rhs)], 
+        Ast::Call(_, args) => indexed("args", args), 
+        Ast::Ternary(cond, then, otherwise) => {
+            vec![("cond".into(), cond), ("then".into(), then), ("else".into(), otherwise)]
+        }
+        Ast::Assign(_, value) => vec![("value".into(), value)], 
+        Ast::Seq(statements) => indexed("statements", statements), 
+        Ast::Let { value, body, .. } => vec![("value".into(), value), ("body".into(), body)], 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn diff_str(expected: &str, actual: &str) -> Option<String> {
+        structural_diff(&parse(expected).unwrap(), &parse(actual).unwrap())
+    }
+
+    #[test]
+    fn equal() {
+        assert_eq!(diff_str("1 + 2", "1+2"), None);
+        assert_eq!(diff_str("(1 + 2) * 3", "1+2 * 3"), None);
+        assert_eq!(diff_str("1.50 + x", "1.5 + x"), None);
+        assert_eq!(diff_str("max(1, 2); y = 3", "max(1,2);y=3"), None);
+    }
+
+    #[test]
+    fn differences() {
+        assert_eq!(diff_str("1 + 2", "1 * 2").as_deref(), Some("at root: expected + got *"));
+        assert_eq!(diff_str("1.0 + x", "1 + x").as_deref(), Some("at lhs: expected 1.0 got 1"));
+        assert_eq!(diff_str("1 - 2 + 3", "1 - 2*3").as_deref(), Some("at root: expected + got -"));
+        assert_eq!(diff_str("1 * (2 + 3)", "1 * (2 * 3)").as_deref(), Some("at rhs: expected + got *"));
+        assert_eq!(
+            diff_str("(1 * (2 + 3)) - 4", "(1 * (2 * 3)) - 4").as_deref(), 
+            Some("at lhs.rhs: expected + got *"), 
+        );
+        assert_eq!(diff_str("-x", "-y").as_deref(), Some("at operand: expected x got y"));
+        assert_eq!(diff_str("-x", "+x").as_deref(), Some("at root: expected neg got pos"));
+        assert_eq!(diff_str("x", "2").as_deref(), Some("at root: expected x got 2"));
+        assert_eq!(diff_str("max(1, 2)", "max(1, 3)").as_deref(), Some("at args[1]: expected 2 got 3"));
+        assert_eq!(
+            diff_str("max(1, 2)", "max(1, 2, 3)").as_deref(), 
+            Some("at root: expected 2 operands of max got 3"), 
+        );
+        assert_eq!(diff_str("a ? b : c", "a ? b : d").as_deref(), Some("at else: expected c got d"));
+        assert_eq!(
+            diff_str("x = 1; x + 1", "x = 2; x + 1").as_deref(), 
+            Some("at statements[0].value: expected 1 got 2"), 
+        );
+    }
+}
@@ -0,0 +1,918 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+use crate::{Ast, BinOp, Numeric, UnOp};
+
+/// Values of the variables in an expression, keyed by name. This is a `HashMap` with the `std` feature and a
+/// `BTreeMap` without it
+#[cfg(feature = "std")]
+pub type Env<T> = std::collections::HashMap<String, T>;
+#[cfg(not(feature = "std"))]
+pub type Env<T> = alloc::collections::BTreeMap<String, T>;
+
+/// The floating point functions that `core` lacks, which are provided by `libm` without the `std` feature.
+/// Tests link `std` regardless, so its inherent methods are used there
+#[cfg(not(any(feature = "std", test)))]
+trait Float {
+    fn sqrt(self) -> Self;
+    fn ln(self) -> Self;
+    fn log(self, base: Self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn exp(self) -> Self;
+    fn fract(self) -> Self;
+    fn floor(self) -> Self;
+    fn powf(self, y: Self) -> Self;
+}
+
+#[cfg(not(any(feature = "std", test)))]
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn ln(self) -> f64 {
+        libm::log(self)
+    }
+
+    fn log(self, base: f64) -> f64 {
+        libm::log(self) / libm::log(base)
+    }
+
+    fn log2(self) -> f64 {
+        libm::log2(self)
+    }
+
+    fn log10(self) -> f64 {
+        libm::log10(self)
+    }
+
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+
+    fn tan(self) -> f64 {
+        libm::tan(self)
+    }
+
+    fn exp(self) -> f64 {
+        libm::exp(self)
+    }
+
+    fn fract(self) -> f64 {
+        self - libm::trunc(self)
+    }
+
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+
+    fn powf(self, y: f64) -> f64 {
+        libm::pow(self, y)
+    }
+}
+
+/// Reasons for which an AST can't be evaluated to a number
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    /// The rhs of a division or modulo operation evaluated to zero
+    DivisionByZero, 
+    /// The argument to `sqrt` evaluated to a negative number
+    NegativeSqrt(f64), 
+    /// The argument to `ln` or `log` evaluated to a non-positive number
+    NonPositiveLog(f64), 
+    /// The operand of a factorial evaluated to something other than a non-negative integer
+    InvalidFactorial(f64), 
+    /// The operator isn't one the evaluator knows how to compute
+    UnknownOperator(String), 
+    /// The variable has no value in the environment
+    UnboundVariable(String), 
+    /// The function was called with a number of arguments it doesn't accept
+    InvalidArity(String, usize), 
+    /// The result of an integer operation doesn't fit in an `i64`
+    Overflow, 
+    /// A literal of an integer evaluation isn't an integer
+    NonInteger(f64), 
+    /// The dividend of an exact integer division isn't a multiple of the divisor
+    InexactDivision(i64, i64), 
+    /// A sub-expression evaluated to NaN, e.g. `inf - inf`, or a variable or constant is NaN
+    NotANumber, 
+    /// A literal can't be represented by the number type of `eval_numeric`, e.g. `inf` as a decimal
+    Unrepresentable(f64), 
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero => write!(f, "division by zero"), 
+            EvalError::NegativeSqrt(x) => write!(f, "square root of negative number {x}"), 
+            EvalError::NonPositiveLog(x) => write!(f, "logarithm of non-positive number {x}"), 
+            EvalError::InvalidFactorial(x) => write!(f, "factorial of non-natural number {x}"), 
+            EvalError::UnknownOperator(op) => write!(f, "unknown operator `{op}`"), 
+            EvalError::UnboundVariable(name) => write!(f, "unbound variable `{name}`"), 
+            EvalError::InvalidArity(name, arity) => {
+                write!(f, "function `{name}` can't take {arity} arguments")
+            }
+            EvalError::Overflow => write!(f, "integer overflow"), 
+            EvalError::NonInteger(x) => write!(f, "non-integer literal {x}"), 
+            EvalError::InexactDivision(x, y) => write!(f, "{x} isn't divisible by {y}"), 
+            EvalError::NotANumber => write!(f, "result is not a number"), 
+            EvalError::Unrepresentable(x) => write!(f, "literal {x} can't be represented"), 
+        }
+    }
+}
+
+impl core::error::Error for EvalError {}
+
+/// Computes the numerical value of an AST without any variables
+pub fn eval(ast: &Ast) -> Result<f64, EvalError> {
+    eval_in(ast, &mut Env::new())
+}
+
+/// Computes the numerical value of an AST, resolving variables from the environment. Infinities propagate
+/// like they do for floats, e.g. `inf + 1 = inf`, whereas NaN is never returned: any sub-expression that
+/// evaluates to NaN fails with `EvalError::NotANumber`, such that it can't be silently compared or discarded.
+/// Assignments bind variables for the rest of the evaluation without affecting `env`
+pub fn eval_with(ast: &Ast, env: &Env<f64>) -> Result<f64, EvalError> {
+    eval_in(ast, &mut env.clone())
+}
+
+/// Computes the numerical value of an AST like `eval_with`, except that assignments are kept in the
+/// environment, e.g. to carry variables from one input to the next
+pub fn eval_in(ast: &Ast, env: &mut Env<f64>) -> Result<f64, EvalError> {
+    eval_float_in(ast, env, ZeroDivision::Error)
+}
+
+/// What `eval_with_division` computes for a division or modulo operation whose rhs is zero, or for zero
+/// raised to a negative power
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroDivision {
+    /// Fails with `EvalError::DivisionByZero`
+    #[default]
+    Error, 
+    /// Follows IEEE 754, e.g. `1 / 0 = inf`, `-1 / 0 = -inf`, `0 ^ -1 = inf` and `0 / 0 = NaN`. Since NaN is
+    /// then a valid result, it's returned rather than failing with `EvalError::NotANumber`
+    Ieee, 
+}
+
+/// Computes the numerical value of an AST like `eval_with`, handling division by zero as specified
+pub fn eval_with_division(
+    ast: &Ast, 
+    env: &Env<f64>, 
+    zero_division: ZeroDivision, 
+) -> Result<f64, EvalError> {
+    eval_float_in(ast, &mut env.clone(), zero_division)
+}
+
+/// Implementation of `eval_in` and `eval_with_division`
+fn eval_float_in(ast: &Ast, env: &mut Env<f64>, zero_division: ZeroDivision) -> Result<f64, EvalError> {
+    let value = match ast {
+        Ast::Literal(number) => number.value, 
+        Ast::Variable(name) => *env
+            .get(name)
+            .ok_or_else(|| EvalError::UnboundVariable(name.clone()))?, 
+        Ast::Unary { op, operand } => {
+            let x = eval_float_in(operand, env, zero_division)?;
+            match op {
+                UnOp::Neg => -x, 
+                UnOp::Plus => x, 
+                UnOp::Sqrt if x < 0.0 => return Err(EvalError::NegativeSqrt(x)), 
+                UnOp::Sqrt => x.sqrt(), 
+                UnOp::Ln | UnOp::Log if x <= 0.0 => return Err(EvalError::NonPositiveLog(x)), 
+                UnOp::Ln => x.ln(), 
+                UnOp::Log => x.log10(), 
+                UnOp::Sin => x.sin(), 
+                UnOp::Cos => x.cos(), 
+                UnOp::Tan => x.tan(), 
+                UnOp::Abs => x.abs(), 
+                UnOp::Exp => x.exp(), 
+                UnOp::Fact if x < 0.0 || x.fract() != 0.0 => return Err(EvalError::InvalidFactorial(x)), 
+                UnOp::Fact => factorial(x), 
+                UnOp::Percent => x / 100.0, 
+                // bitwise operators are only defined for integers, see `eval_int`
+                UnOp::Not => return Err(EvalError::UnknownOperator(op.name().into())), 
+                UnOp::Function(name) => return Err(EvalError::UnknownOperator(name.clone())), 
+            }
+        }
+        Ast::Binary { op, lhs, rhs, .. } => {
+            let x = eval_float_in(lhs, env, zero_division)?;
+            let y = eval_float_in(rhs, env, zero_division)?;
+            match op {
+                BinOp::Add => x + y, 
+                BinOp::Sub => x - y, 
+                BinOp::Mul => x * y, 
+                BinOp::Div | BinOp::FloorDiv | BinOp::Rem
+                    if y == 0.0 && zero_division == ZeroDivision::Error =>
+                {
+                    return Err(EvalError::DivisionByZero)
+                }
+                BinOp::Div => x / y, 
+                BinOp::FloorDiv => (x / y).floor(), 
+                BinOp::Rem => x % y, 
+                BinOp::Pow if x == 0.0 && y < 0.0 && zero_division == ZeroDivision::Error => {
+                    return Err(EvalError::DivisionByZero)
+                }
+                BinOp::Pow => x.powf(y), 
+                BinOp::Lt => (x < y) as u8 as f64, 
+                BinOp::Gt => (x > y) as u8 as f64, 
+                BinOp::Le => (x <= y) as u8 as f64, 
+                BinOp::Ge => (x >= y) as u8 as f64, 
+                BinOp::BitAnd | BinOp::BitOr | BinOp::Shl | BinOp::Shr => {
+                    return Err(EvalError::UnknownOperator(op.symbol().into()))
+                }
+            }
+        }
+        Ast::Call(name, args) => {
+            let args = args.iter()
+                .map(|arg| eval_float_in(arg, env, zero_division))
+                .collect::<Result<Vec<_>, _>>()?;
+            match (name.as_str(), &args[..]) {
+                ("log", &[x, base]) if x <= 0.0 || base <= 0.0 => {
+                    return Err(EvalError::NonPositiveLog(x.min(base)))
+                }
+                ("log", &[x, base]) => log(x, base), 
+                ("log", &[x]) if x <= 0.0 => return Err(EvalError::NonPositiveLog(x)), 
+                ("log", &[x]) => x.log10(), 
+                ("max", [_, ..]) => args.iter().copied().fold(f64::NEG_INFINITY, f64::max), 
+                ("min", [_, ..]) => args.iter().copied().fold(f64::INFINITY, f64::min), 
+                ("log" | "max" | "min", _) => return Err(EvalError::InvalidArity(name.clone(), args.len())), 
+                _ if UnOp::BUILTIN.iter().any(|op| op.name() == name) => {
+                    return Err(EvalError::InvalidArity(name.clone(), args.len()))
+                }
+                _ => return Err(EvalError::UnknownOperator(name.clone())), 
+            }
+        }
+        // only the branch that's taken is evaluated, such that e.g. `x > 0 ? ln x : 0` can't fail
+        Ast::Ternary(cond, then, otherwise) => match eval_float_in(cond, env, zero_division)? != 0.0 {
+            true => eval_float_in(then, env, zero_division)?, 
+            false => eval_float_in(otherwise, env, zero_division)?, 
+        }
+        Ast::Assign(name, value) => {
+            let value = eval_float_in(value, env, zero_division)?;
+            env.insert(name.clone(), value);
+            value
+        }
+        // an empty sequence has no statement to take the value of, so it's 0 like a false condition
+        Ast::Seq(statements) => {
+            let mut value = 0.0;
+            for statement in statements {
+                value = eval_float_in(statement, env, zero_division)?;
+            }
+            value
+        }
+        Ast::Let { name, value, body } => {
+            let value = eval_float_in(value, env, zero_division)?;
+            scoped(env, name, value, |env| eval_float_in(body, env, zero_division))?
+        }
+    };
+    if value.is_nan() && zero_division == ZeroDivision::Error {
+        return Err(EvalError::NotANumber)
+    }
+    Ok(value)
+}
+
+/// How `eval_int` rounds the quotient of a division that isn't exact
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntDivision {
+    /// Rounds towards negative infinity, e.g. `7 / 2 = 3` and `-7 / 2 = -4`
+    #[default]
+    Floor, 
+    /// Fails with `EvalError::InexactDivision` unless the divisor divides the dividend
+    Exact, 
+}
+
+/// Computes the value of an AST without any variables under integer semantics, with floor division. See
+/// `eval_int_with`
+pub fn eval_int(ast: &Ast) -> Result<i64, EvalError> {
+    eval_int_with(ast, &Env::new(), IntDivision::Floor)
+}
+
+/// Computes the value of an AST under integer semantics, resolving variables from the environment. All
+/// arithmetic is checked, failing with `EvalError::Overflow` rather than wrapping, and literals must be
+/// integers. Negative exponents divide 1 by the power, rounding like any other division, and `%` takes the
+/// sign of the dividend like it does for floats. Operators without integer results such as `sqrt` and `log`
+/// fail with `EvalError::UnknownOperator`, whereas the bitwise operators `&`, `|`, `<<`, `>>` and `~` are
+/// only evaluated here
+pub fn eval_int_with(
+    ast: &Ast, 
+    env: &Env<i64>, 
+    division: IntDivision, 
+) -> Result<i64, EvalError> {
+    eval_int_in(ast, &mut env.clone(), division)
+}
+
+/// Implementation of `eval_int_with` that keeps assignments in the environment
+fn eval_int_in(ast: &Ast, env: &mut Env<i64>, division: IntDivision) -> Result<i64, EvalError> {
+    let value = match ast {
+        Ast::Literal(number) => {
+            let x = number.value;
+            if x.fract() != 0.0 || x.is_nan() {
+                return Err(EvalError::NonInteger(x))
+            }
+            // 2^63 is exactly representable, unlike `i64::MAX`, which rounds up to it
+            let bound = -(i64::MIN as f64);
+            if !(-bound..bound).contains(&x) {
+                return Err(EvalError::Overflow)
+            }
+            x as i64
+        }
+        Ast::Variable(name) => *env
+            .get(name)
+            .ok_or_else(|| EvalError::UnboundVariable(name.clone()))?, 
+        Ast::Unary { op, operand } => {
+            let x = eval_int_in(operand, env, division)?;
+            match op {
+                UnOp::Neg => x.checked_neg().ok_or(EvalError::Overflow)?, 
+                UnOp::Plus => x, 
+                UnOp::Abs => x.checked_abs().ok_or(EvalError::Overflow)?, 
+                UnOp::Fact if x < 0 => return Err(EvalError::InvalidFactorial(x as f64)), 
+                UnOp::Fact => (1..=x)
+                    .try_fold(1i64, i64::checked_mul)
+                    .ok_or(EvalError::Overflow)?, 
+                UnOp::Percent => divide_int(x, 100, division)?, 
+                UnOp::Not => !x, 
+                _ => return Err(EvalError::UnknownOperator(op.name().into())), 
+            }
+        }
+        Ast::Binary { op, lhs, rhs, .. } => {
+            let x = eval_int_in(lhs, env, division)?;
+            let y = eval_int_in(rhs, env, division)?;
+            match op {
+                BinOp::Add => x.checked_add(y).ok_or(EvalError::Overflow)?, 
+                BinOp::Sub => x.checked_sub(y).ok_or(EvalError::Overflow)?, 
+                BinOp::Mul => x.checked_mul(y).ok_or(EvalError::Overflow)?, 
+                BinOp::Div => divide_int(x, y, division)?, 
+                BinOp::FloorDiv => divide_int(x, y, IntDivision::Floor)?, 
+                BinOp::Rem if y == 0 => return Err(EvalError::DivisionByZero), 
+                BinOp::Rem => x.checked_rem(y).ok_or(EvalError::Overflow)?, 
+                BinOp::Pow => pow_int(x, y, division)?, 
+                BinOp::Lt => (x < y) as i64, 
+                BinOp::Gt => (x > y) as i64, 
+                BinOp::Le => (x <= y) as i64, 
+                BinOp::Ge => (x >= y) as i64, 
+                BinOp::BitAnd => x & y, 
+                BinOp::BitOr => x | y, 
+                BinOp::Shl | BinOp::Shr => shift_int(x, y, *op)?, 
+            }
+        }
+        Ast::Call(name, args) => {
+            let args = args.iter()
+                .map(|arg| eval_int_in(arg, env, division))
+                .collect::<Result<Vec<_>, _>>()?;
+            match (name.as_str(), &args[..]) {
+                // the argument lists are non-empty, so there's always a max and min
+                ("max", [_, ..]) => args.iter().copied().max().unwrap(), 
+                ("min", [_, ..]) => args.iter().copied().min().unwrap(), 
+                ("max" | "min", _) => return Err(EvalError::InvalidArity(name.clone(), args.len())), 
+                _ => return Err(EvalError::UnknownOperator(name.clone())), 
+            }
+        }
+        Ast::Ternary(cond, then, otherwise) => match eval_int_in(cond, env, division)? != 0 {
+            true => eval_int_in(then, env, division)?, 
+            false => eval_int_in(otherwise, env, division)?, 
+        }
+        Ast::Assign(name, value) => {
+            let value = eval_int_in(value, env, division)?;
+            env.insert(name.clone(), value);
+            value
+        }
+        Ast::Seq(statements) => {
+            let mut value = 0;
+            for statement in statements {
+                value = eval_int_in(statement, env, division)?;
+            }
+            value
+        }
+        Ast::Let { name, value, body } => {
+            let value = eval_int_in(value, env, division)?;
+            scoped(env, name, value, |env| eval_int_in(body, env, division))?
+        }
+    };
+    Ok(value)
+}
+
+/// Computes the value of an AST without any variables in the number type `N`. See `eval_numeric_with`
+pub fn eval_numeric<N: Numeric>(ast: &Ast) -> Result<N, EvalError> {
+    eval_numeric_with(ast, &Env::new())
+}
+
+/// Computes the value of an AST in the number type `N`, resolving variables from the environment, e.g. with
+/// arbitrary precision using `BigDecimal`. Only arithmetic, comparisons, `abs`, `sqrt`, `max` and `min` are
+/// computed, whereas other functions fail with `EvalError::UnknownOperator`. Literals that are written as
+/// decimals are converted from their lexemes, and others such as constants from their values
+pub fn eval_numeric_with<N: Numeric>(ast: &Ast, env: &Env<N>) -> Result<N, EvalError> {
+    eval_numeric_in(ast, &mut env.clone())
+}
+
+/// Implementation of `eval_numeric_with` that keeps assignments in the environment
+fn eval_numeric_in<N: Numeric>(ast: &Ast, env: &mut Env<N>) -> Result<N, EvalError> {
+    let zero = N::from_i64(0);
+    let value = match ast {
+        // the lexeme is only used if it means the same as a decimal in Rust's syntax, which isn't the case
+        // for other bases or with the separators of other locales, e.g. `1.000` as a thousand
+        Ast::Literal(number) => number.lexeme.as_deref()
+            .filter(|lexeme| f64::from_decimal(lexeme) == Some(number.value))
+            .and_then(N::from_decimal)
+            .or_else(|| N::from_f64(number.value))
+            .ok_or(EvalError::Unrepresentable(number.value))?, 
+        Ast::Variable(name) => env
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EvalError::UnboundVariable(name.clone()))?, 
+        Ast::Unary { op, operand } => {
+            let x = eval_numeric_in(operand, env)?;
+            match op {
+                UnOp::Neg => -x, 
+                UnOp::Plus => x, 
+                UnOp::Abs if x < zero => -x, 
+                UnOp::Abs => x, 
+                UnOp::Sqrt => x.square_root()?, 
+                UnOp::Percent => x / N::from_i64(100), 
+                _ => return Err(EvalError::UnknownOperator(op.name().into())), 
+            }
+        }
+        Ast::Binary { op, lhs, rhs, .. } => {
+            let x = eval_numeric_in(lhs, env)?;
+            let y = eval_numeric_in(rhs, env)?;
+            match op {
+                BinOp::Add => x + y, 
+                BinOp::Sub => x - y, 
+                BinOp::Mul => x * y, 
+                BinOp::Div | BinOp::FloorDiv | BinOp::Rem if y == zero => {
+                    return Err(EvalError::DivisionByZero)
+                }
+                BinOp::Div => x / y, 
+                BinOp::FloorDiv => (x / y).floor(), 
+                BinOp::Rem => x % y, 
+                BinOp::Pow => x.power(&y)?, 
+                BinOp::Lt => N::from_i64((x < y) as i64), 
+                BinOp::Gt => N::from_i64((x > y) as i64), 
+                BinOp::Le => N::from_i64((x <= y) as i64), 
+                BinOp::Ge => N::from_i64((x >= y) as i64), 
+                BinOp::BitAnd | BinOp::BitOr | BinOp::Shl | BinOp::Shr => {
+                    return Err(EvalError::UnknownOperator(op.symbol().into()))
+                }
+            }
+        }
+        Ast::Call(name, args) => {
+            let args = args.iter()
+                .map(|arg| eval_numeric_in(arg, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            let pick = |max: bool| args.iter()
+                .cloned()
+                .reduce(|x, y| if (y > x) == max { y } else { x })
+                .ok_or_else(|| EvalError::InvalidArity(name.clone(), 0));
+            match name.as_str() {
+                "max" => pick(true)?, 
+                "min" => pick(false)?, 
+                _ => return Err(EvalError::UnknownOperator(name.clone())), 
+            }
+        }
+        Ast::Ternary(cond, then, otherwise) => match eval_numeric_in(cond, env)? != zero {
+            true => eval_numeric_in(then, env)?, 
+            false => eval_numeric_in(otherwise, env)?, 
+        }
+        Ast::Assign(name, value) => {
+            let value = eval_numeric_in(value, env)?;
+            env.insert(name.clone(), value.clone());
+            value
+        }
+        Ast::Seq(statements) => {
+            let mut value = zero;
+            for statement in statements {
+                value = eval_numeric_in(statement, env)?;
+            }
+            value
+        }
+        Ast::Let { name, value, body } => {
+            let value = eval_numeric_in(value, env)?;
+            scoped(env, name, value, |env| eval_numeric_in(body, env))?
+        }
+    };
+    if value.is_nan() {
+        return Err(EvalError::NotANumber)
+    }
+    Ok(value)
+}
+
+/// Calls `f` with `name` bound to `value` in the environment, after which the name is bound to whatever it
+/// was before, if anything. Assignments to the name within `f` are discarded along with the binding
+pub(crate) fn scoped<T, R>(env: &mut Env<T>, name: &str, value: T, f: impl FnOnce(&mut Env<T>) -> R) -> R {
+    let shadowed = env.insert(name.into(), value);
+    let result = f(env);
+    match shadowed {
+        Some(shadowed) => {
+            env.insert(name.into(), shadowed);
+        }
+        None => {
+            env.remove(name);
+        }
+    }
+    result
+}
+
+/// Divides two integers, rounding the quotient according to `division`
+fn divide_int(x: i64, y: i64, division: IntDivision) -> Result<i64, EvalError> {
+    if y == 0 {
+        return Err(EvalError::DivisionByZero)
+    }
+    let quotient = x.checked_div(y).ok_or(EvalError::Overflow)?;
+    let remainder = x % y;
+    match division {
+        _ if remainder == 0 => Ok(quotient), 
+        IntDivision::Exact => Err(EvalError::InexactDivision(x, y)), 
+        IntDivision::Floor if (remainder < 0) != (y < 0) => Ok(quotient - 1), 
+        IntDivision::Floor => Ok(quotient), 
+    }
+}
+
+/// Shifts `x` by `y` bits, failing with `EvalError::Overflow` if `y` isn't in `0..64` or if a left shift
+/// drops any bits that differ from the sign, e.g. `1 << 63`
+fn shift_int(x: i64, y: i64, op: BinOp) -> Result<i64, EvalError> {
+    let y = u32::try_from(y).map_err(|_| EvalError::Overflow)?;
+    match op {
+        BinOp::Shl => x.checked_shl(y).filter(|shifted| shifted >> y == x), 
+        _ => x.checked_shr(y), 
+    }
+    .ok_or(EvalError::Overflow)
+}
+
+/// Raises an integer to an integer power, where a negative exponent divides 1 by the power
+fn pow_int(x: i64, y: i64, division: IntDivision) -> Result<i64, EvalError> {
+    // powers of 0, 1 and -1 never overflow, so they're computed for any exponent
+    let power = match x {
+        0 if y == 0 => 1, 
+        0 | 1 => x, 
+        -1 if y % 2 == 0 => 1, 
+        -1 => -1, 
+        _ => u32::try_from(y.unsigned_abs())
+            .ok()
+            .and_then(|exponent| x.checked_pow(exponent))
+            .ok_or(EvalError::Overflow)?, 
+    };
+    match y < 0 {
+        true => divide_int(1, power, division), 
+        false => Ok(power), 
+    }
+}
+
+/// Computes the logarithm of `x` in the given base. Bases 2 and 10 use their dedicated functions, which are
+/// exact for powers of the base, e.g. `log(1000, 10) = 3` rather than `2.9999999999999996`
+fn log(x: f64, base: f64) -> f64 {
+    match base {
+        2.0 => x.log2(), 
+        10.0 => x.log10(), 
+        _ => x.log(base), 
+    }
+}
+
+/// Computes the factorial of a non-negative integer
+fn factorial(x: f64) -> f64 {
+    // 171! and anything above overflows, so there's no need to multiply that far
+    if x > 170.0 {
+        return f64::INFINITY
+    }
+    (1..=x as u64)
+        .map(|i| i as f64)
+        .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, Chaining, ParseOptions};
+
+    fn eval_str(input: &str) -> Result<f64, EvalError> {
+        eval(&parse(input).unwrap())
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(eval_str("1.2 + 3.4"), Ok(1.2 + 3.4));
+        assert_eq!(eval_str("1*    3+4   -   5/6"), Ok(1.0 * ((3.0 + 4.0) - (5.0 / 6.0))));
+        assert_eq!(eval_str("1*    3+4    -   5/6"), Ok((1.0 * (3.0 + 4.0)) - (5.0 / 6.0)));
+        assert_eq!(eval_str("sqrt  1 + 3"), Ok(2.0));
+        assert_eq!(eval_str("-2 ^ 2"), Ok(4.0));
+        assert_eq!(eval_str("- 2 ^ 2"), Ok(-4.0));
+        assert_eq!(eval_str("10 % 3"), Ok(1.0));
+        assert_eq!(eval_str("-+5"), Ok(-5.0));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval_str("sin 0"), Ok(0.0));
+        assert_eq!(eval_str("cos 0"), Ok(1.0));
+        assert_eq!(eval_str("tan 0"), Ok(0.0));
+        assert_eq!(eval_str("ln 1"), Ok(0.0));
+        assert_eq!(eval_str("log 100"), Ok(2.0));
+        assert_eq!(eval_str("abs  0 - 2"), Ok(2.0));
+        assert_eq!(eval_str("exp 0"), Ok(1.0));
+        assert_eq!(eval_str("sin cos 0"), Ok(1f64.sin()));
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(eval_str("log(8, 2)"), Ok(3.0));
+        assert_eq!(eval_str("log(1000, 10)"), Ok(3.0));
+        assert_eq!(eval_str("log(81, 3)"), Ok(4.0));
+        assert_eq!(eval_str("log(100)"), Ok(2.0));
+        assert_eq!(eval_str("max(1, 2)"), Ok(2.0));
+        assert_eq!(eval_str("min(3, 1, 2)"), Ok(1.0));
+        assert_eq!(eval_str("max(-1)"), Ok(-1.0));
+        assert_eq!(eval_str("1 + max(2, 3) * 4"), Ok(13.0));
+    }
+
+    #[test]
+    fn constants() {
+        use std::f64::consts;
+        assert_eq!(eval_str("pi"), Ok(consts::PI));
+        assert_eq!(eval_str("2 * pi"), Ok(consts::TAU));
+        assert_eq!(eval_str("ln e"), Ok(1.0));
+        assert_eq!(eval_str("tau / 2"), Ok(consts::PI));
+    }
+
+    #[test]
+    fn variables() {
+        let env = Env::from([("x".into(), 3.0), ("y".into(), 4.0)]);
+        let eval_env = |input| eval_with(&parse(input).unwrap(), &env);
+        assert_eq!(eval_env("x + y * 2"), Ok(11.0));
+        assert_eq!(eval_env("sqrt  x * 3 + y * 4"), Ok(5.0));
+        assert_eq!(eval_env("x + z"), Err(EvalError::UnboundVariable("z".into())));
+        assert_eq!(eval_str("x"), Err(EvalError::UnboundVariable("x".into())));
+    }
+
+    #[test]
+    fn factorial() {
+        assert_eq!(eval_str("0!"), Ok(1.0));
+        assert_eq!(eval_str("5!"), Ok(120.0));
+        assert_eq!(eval_str("3! + 2"), Ok(8.0));
+        assert_eq!(eval_str("3!!"), Ok(720.0));
+        assert_eq!(eval_str("171!"), Ok(f64::INFINITY));
+        assert_eq!(eval_str("(0 - 1)!"), Err(EvalError::InvalidFactorial(-1.0)));
+        assert_eq!(eval_str("1.5!"), Err(EvalError::InvalidFactorial(1.5)));
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        let options = ParseOptions { chaining: Chaining::Conjunction, ..ParseOptions::default() };
+        let expr = |input| parse_with(input, &options).unwrap();
+        assert_eq!(eval(&expr("1 < 2 < 3")), Ok(1.0));
+        assert_eq!(eval(&expr("3 > 2 > 1")), Ok(1.0));
+        assert_eq!(eval(&expr("3 > 2  >  1")), Ok(0.0));
+        assert_eq!(eval(&expr("1 < 3 < 2")), Ok(0.0));
+        assert_eq!(eval(&expr("2 < 1 < x")), Ok(0.0));
+    }
+
+    #[test]
+    fn percent() {
+        let options = ParseOptions { percent: true, ..ParseOptions::default() };
+        let expr = |input| parse_with(input, &options).unwrap();
+        assert_eq!(eval(&expr("50%")), Ok(0.5));
+        assert_eq!(eval(&expr("200 + 10%")), Ok(200.1));
+        assert_eq!(eval(&expr("7 % 3")), Ok(1.0));
+        assert_eq!(eval_int(&expr("250%")), Ok(2));
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(eval_str("1 < 2"), Ok(1.0));
+        assert_eq!(eval_str("2 < 1"), Ok(0.0));
+        assert_eq!(eval_str("3 > 2 + 2"), Ok(0.0));
+        assert_eq!(eval_str("1 + 2 > 2 + 0"), Ok(1.0));
+        assert_eq!(eval_str("2 <= 2"), Ok(1.0));
+        assert_eq!(eval_str("1 >= 2"), Ok(0.0));
+    }
+
+    #[test]
+    fn integers() {
+        let eval_int_str = |input| eval_int(&parse(input).unwrap());
+        assert_eq!(eval_int_str("7 / 2"), Ok(3));
+        assert_eq!(eval_int_str("-7 / 2"), Ok(-4));
+        assert_eq!(eval_int_str("-7 % 2"), Ok(-1));
+        assert_eq!(eval_int_str("2 ^ 62 + 5!"), Ok((1 << 62) + 120));
+        assert_eq!(eval_int_str("2 ^ -1"), Ok(0));
+        assert_eq!(eval_int_str("(0 - 1) ^ 99999999999"), Ok(-1));
+        assert_eq!(eval_int_str("max(1, 3 * 4, 5)"), Ok(12));
+        assert_eq!(eval_int_str("abs  0 - 3   <   2"), Ok(0));
+
+        let exact = |input| eval_int_with(&parse(input).unwrap(), &Env::new(), IntDivision::Exact);
+        assert_eq!(exact("8 / 2"), Ok(4));
+        assert_eq!(exact("7 / 2"), Err(EvalError::InexactDivision(7, 2)));
+
+        let env = Env::from([("x".into(), 6)]);
+        assert_eq!(eval_int_with(&parse("x / 4").unwrap(), &env, IntDivision::Floor), Ok(1));
+
+        assert_eq!(eval_int_str("2 ^ 63"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("10 ^ 10 ^ 10"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("21!"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("9223372036854775807 + 1"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("1.5 * 2"), Err(EvalError::NonInteger(1.5)));
+        assert_eq!(eval_int_str("1e100"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_int_str("sqrt 4"), Err(EvalError::UnknownOperator("sqrt".into())));
+    }
+
+    #[test]
+    fn bitwise() {
+        let eval_int_str = |input| eval_int(&parse(input).unwrap());
+        assert_eq!(eval_int_str("6 & 3"), Ok(2));
+        assert_eq!(eval_int_str("6 | 3"), Ok(7));
+        assert_eq!(eval_int_str("1 << 4"), Ok(16));
+        assert_eq!(eval_int_str("-16 >> 2"), Ok(-4));
+        assert_eq!(eval_int_str("~0"), Ok(-1));
+        assert_eq!(eval_int_str("~5 & 0xff"), Ok(250));
+        assert_eq!(eval_int_str("1 << 62"), Ok(1 << 62));
+        assert_eq!(eval_int_str("-1 << 63"), Ok(i64::MIN));
+
+        assert_eq!(eval_int_str("1 << 63"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("1 >> 64"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("1 << -1"), Err(EvalError::Overflow));
+        assert_eq!(eval_int_str("1.5 & 1"), Err(EvalError::NonInteger(1.5)));
+        assert_eq!(eval_str("6 & 3"), Err(EvalError::UnknownOperator("&".into())));
+        assert_eq!(eval_str("~0"), Err(EvalError::UnknownOperator("~".into())));
+    }
+
+    #[test]
+    fn floor_division() {
+        assert_eq!(eval_str("7 // 2"), Ok(3.0));
+        assert_eq!(eval_str("7 // 2 + 1"), Ok(4.0));
+        assert_eq!(eval_str("7 // 2+1"), Ok(2.0));
+        assert_eq!(eval_str("-7 // 2"), Ok(-4.0));
+        assert_eq!(eval_str("7 // -2"), Ok(-4.0));
+        assert_eq!(eval_str("7.5 // 0.5 * 2"), Ok(30.0));
+        assert_eq!(eval_str("1 // 0"), Err(EvalError::DivisionByZero));
+        let ieee = eval_with_division(&parse("1 // 0").unwrap(), &Env::new(), ZeroDivision::Ieee);
+        assert_eq!(ieee, Ok(f64::INFINITY));
+
+        // integer floor division rounds down regardless of how `/` rounds
+        let eval_exact = |input| eval_int_with(&parse(input).unwrap(), &Env::new(), IntDivision::Exact);
+        assert_eq!(eval_exact("-7 // 2"), Ok(-4));
+        assert_eq!(eval_exact("-7 / 2"), Err(EvalError::InexactDivision(-7, 2)));
+        assert_eq!(eval_numeric(&parse("7 // -2").unwrap()), Ok(-4.0));
+    }
+
+    #[test]
+    fn ternary() {
+        assert_eq!(eval_str("1 > 0 ? 2 : 3"), Ok(2.0));
+        assert_eq!(eval_str("0 ? 2 : 3"), Ok(3.0));
+        assert_eq!(eval_str("0.5 ? 2 : 3"), Ok(2.0));
+        assert_eq!(eval_str("0 ? 1 : 0 ? 2 : 3"), Ok(3.0));
+        assert_eq!(eval_str("1 ? 1 / 2 : 1 / 0"), Ok(0.5));
+        assert_eq!(eval_int(&parse("2 > 1 ? 7 / 2 : 0").unwrap()), Ok(3));
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(eval_str("x = 3; y = x + 1; y * 2"), Ok(8.0));
+        assert_eq!(eval_str("x = 2"), Ok(2.0));
+        assert_eq!(eval_str("x = 1; x = x + 1; x"), Ok(2.0));
+        assert_eq!(eval_str("x + 1; x = 1"), Err(EvalError::UnboundVariable("x".into())));
+        assert_eq!(eval_int(&parse("x = 7; x / 2").unwrap()), Ok(3));
+
+        // `eval_with` leaves the environment as is, whereas `eval_in` keeps the assignments
+        let mut env = Env::from([("x".into(), 1.0)]);
+        assert_eq!(eval_with(&parse("x = x + 1; x").unwrap(), &env), Ok(2.0));
+        assert_eq!(env["x"], 1.0);
+        assert_eq!(eval_in(&parse("x = x + 1; y = 5").unwrap(), &mut env), Ok(5.0));
+        assert_eq!(eval_in(&parse("x * y").unwrap(), &mut env), Ok(10.0));
+    }
+
+    #[test]
+    fn numeric() {
+        let eval_f64 = |input| eval_numeric::<f64>(&parse(input).unwrap());
+        for input in ["1 + 2 * 3", "0.1 + 0.2 - 0.3", "2 ^ -2", "7 % 3 < 2 ? 5 : -abs -3", "max(1, 3, 2)"] {
+            assert_eq!(eval_f64(input), eval_str(input));
+        }
+        assert_eq!(eval_f64("let x = 2 in sqrt x ^ 2"), Ok(2.0));
+        assert_eq!(eval_f64("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_f64("0 ^ -1"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_f64("0 ^ -0.5"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_f64("0 ^ 2"), Ok(0.0));
+        assert_eq!(eval_f64("sqrt -4"), Err(EvalError::NegativeSqrt(-4.0)));
+        assert_eq!(eval_f64("inf - inf"), Err(EvalError::NotANumber));
+        assert_eq!(eval_f64("ln 2"), Err(EvalError::UnknownOperator("ln".into())));
+        assert_eq!(eval_f64("max()"), Err(EvalError::InvalidArity("max".into(), 0)));
+
+        // lexemes that aren't decimals in Rust's syntax are converted from their values instead
+        let mut options = ParseOptions::default();
+        options.lex.decimal_separator = ',';
+        options.lex.grouping_separator = Some('.');
+        let expr = parse_with("1.000,5 + 1.000", &options).unwrap();
+        assert_eq!(eval_numeric(&expr), Ok(2000.5));
+        let env = Env::from([("x".into(), 2.0)]);
+        assert_eq!(eval_numeric_with(&parse("0xff * x").unwrap(), &env), Ok(510.0));
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn big_decimal() {
+        use bigdecimal::BigDecimal;
+
+        let eval_f64 = |input| eval_numeric::<f64>(&parse(input).unwrap());
+        let eval_big = |input| eval_numeric::<BigDecimal>(&parse(input).unwrap());
+        let decimal = |string: &str| Ok(string.parse::<BigDecimal>().unwrap());
+
+        // decimal literals are exact, as is arithmetic on them unless it's rounded to the precision
+        assert_ne!(eval_f64("0.1 + 0.2 - 0.3"), Ok(0.0));
+        assert_eq!(eval_big("0.1 + 0.2 - 0.3"), decimal("0"));
+        assert_eq!(eval_f64("1e20 + 1 - 1e20"), Ok(0.0));
+        assert_eq!(eval_big("1e20 + 1 - 1e20"), decimal("1"));
+        assert_eq!(eval_big("1.5 * 1.5 < 2.25"), decimal("0"));
+
+        assert_eq!(eval_big("2 ^ -2"), decimal("0.25"));
+        assert_eq!(eval_big("0 ^ 0"), decimal("1"));
+        assert_eq!(eval_big("4 ^ 0.5"), decimal("2"));
+        assert_eq!(eval_big("0xff % 10"), decimal("5"));
+        assert_eq!(eval_big("0 ^ -1"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_big("1 % 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_big("sqrt -4"), Err(EvalError::NegativeSqrt(-4.0)));
+        assert_eq!(eval_big("inf"), Err(EvalError::Unrepresentable(f64::INFINITY)));
+
+        // both backends agree on results that are exact in each, and on errors
+        let to_f64 = |result: Result<BigDecimal, EvalError>| result.map(|x| Numeric::to_f64(&x));
+        for input in ["2 ^ -2", "0 ^ 0", "0 ^ 3", "0 ^ -1", "0 ^ -0.5", "(-2) ^ -1", "1 / 0", "7 // -2"] {
+            assert_eq!(to_f64(eval_big(input)), eval_f64(input), "{input}");
+        }
+        assert_eq!(eval_int(&parse("0 ^ -1").unwrap()), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn bindings() {
+        assert_eq!(eval_str("let x = 2 in x * x"), Ok(4.0));
+        assert_eq!(eval_str("let x = 1 in let x = 2 in x"), Ok(2.0));
+        assert_eq!(eval_str("let x = 1 in (let x = 2 in x) + x"), Ok(3.0));
+        assert_eq!(eval_str("let x = 1 in let y = x + 1 in x * y"), Ok(2.0));
+        assert_eq!(eval_str("(let x = 1 in x) + x"), Err(EvalError::UnboundVariable("x".into())));
+        assert_eq!(eval_int(&parse("let x = 7 in x / 2").unwrap()), Ok(3));
+
+        // the binding shadows the environment within the body only
+        let mut env = Env::from([("x".into(), 5.0)]);
+        assert_eq!(eval_in(&parse("(let x = 1 in x) + x").unwrap(), &mut env), Ok(6.0));
+        assert_eq!(eval_in(&parse("let y = 1 in y").unwrap(), &mut env), Ok(1.0));
+        assert_eq!(env, Env::from([("x".into(), 5.0)]));
+    }
+
+    #[test]
+    fn non_finite() {
+        assert_eq!(eval_str("inf + 1"), Ok(f64::INFINITY));
+        assert_eq!(eval_str("-inf < 0"), Ok(1.0));
+        assert_eq!(eval_str("1 / inf"), Ok(0.0));
+        assert_eq!(eval_str("max(1, inf)"), Ok(f64::INFINITY));
+        assert_eq!(eval_str("0 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("0 ^ -1"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("0 ^ -0.5"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("0 ^ 0"), Ok(1.0));
+        assert_eq!(eval_str("inf - inf"), Err(EvalError::NotANumber));
+        assert_eq!(eval_str("0 * inf  <  1"), Err(EvalError::NotANumber));
+        assert_eq!(eval_str("sin inf"), Err(EvalError::NotANumber));
+        assert_eq!(eval_str("-8 ^ 0.5"), Err(EvalError::NotANumber));
+        assert_eq!(eval_str("0 ? inf - inf : 1"), Ok(1.0));
+        assert_eq!(eval_str("nan"), Err(EvalError::UnboundVariable("nan".into())));
+
+        let env = Env::from([("x".into(), f64::NAN)]);
+        assert_eq!(eval_with(&parse("x + 1").unwrap(), &env), Err(EvalError::NotANumber));
+        assert_eq!(eval_int(&parse("inf").unwrap()), Err(EvalError::NonInteger(f64::INFINITY)));
+    }
+
+    #[test]
+    fn zero_division() {
+        let eval_ieee = |input| eval_with_division(&parse(input).unwrap(), &Env::new(), ZeroDivision::Ieee);
+        assert_eq!(eval_str("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("-1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("0 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_ieee("1 / 0"), Ok(f64::INFINITY));
+        assert_eq!(eval_ieee("-1 / 0"), Ok(f64::NEG_INFINITY));
+        assert_eq!(eval_ieee("0 ^ -1"), Ok(f64::INFINITY));
+        assert!(eval_ieee("0 / 0").unwrap().is_nan());
+        assert!(eval_ieee("1 % 0").unwrap().is_nan());
+        assert!(eval_ieee("inf - inf").unwrap().is_nan());
+        assert_eq!(eval_ieee("1 / (1/0)"), Ok(0.0));
+        assert_eq!(eval_ieee("sqrt -4"), Err(EvalError::NegativeSqrt(-4.0)));
+
+        let env = Env::from([("x".into(), 2.0)]);
+        let ast = parse("y = 0; x / y").unwrap();
+        assert_eq!(eval_with_division(&ast, &env, ZeroDivision::Error), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_with_division(&ast, &env, ZeroDivision::Ieee), Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval_str("1 / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("1 %  2 - 2"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval_str("sqrt -4"), Err(EvalError::NegativeSqrt(-4.0)));
+        assert_eq!(eval_str("ln 0"), Err(EvalError::NonPositiveLog(0.0)));
+        assert_eq!(eval_str("log -1"), Err(EvalError::NonPositiveLog(-1.0)));
+        assert_eq!(eval_str("log(8, -2)"), Err(EvalError::NonPositiveLog(-2.0)));
+        assert_eq!(eval_str("log(8, 2, 1)"), Err(EvalError::InvalidArity("log".into(), 3)));
+        assert_eq!(eval_str("max()"), Err(EvalError::InvalidArity("max".into(), 0)));
+        assert_eq!(eval_str("sqrt(4, 9)"), Err(EvalError::InvalidArity("sqrt".into(), 2)));
+        let call = Ast::Call("foo".into(), vec![]);
+        assert_eq!(eval(&call), Err(EvalError::UnknownOperator("foo".into())));
+    }
+}
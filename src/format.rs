@@ -0,0 +1,92 @@
+use alloc::{format, string::{String, ToString}};
+
+/// Notations that `format_number` writes numbers in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// Positional notation without an exponent, like `f64`'s `Display`, e.g. `1500000` and `0.00025`
+    #[default]
+    Plain, 
+    /// Scientific notation with a single digit before the decimal point, e.g. `1.5e6` and `2.5e-4`
+    Scientific, 
+    /// Scientific notation with an exponent that's a multiple of 3 and one to three digits before the decimal
+    /// point, e.g. `1.5e6` and `250e-6`
+    Engineering, 
+}
+
+/// Writes a number in the given notation, using as many digits as needed to represent it exactly like
+/// `f64`'s `Display` does. Infinities and NaN are written as `inf`, `-inf` and `NaN` in any notation
+///
+/// ```
+/// # use space_precedence_parser::{format_number, NumberFormat};
+/// assert_eq!(format_number(0.00025, NumberFormat::Scientific), "2.5e-4");
+/// assert_eq!(format_number(0.00025, NumberFormat::Engineering), "250e-6");
+/// ```
+pub fn format_number(value: f64, format: NumberFormat) -> String {
+    match format {
+        _ if !value.is_finite() => value.to_string(), 
+        NumberFormat::Plain => value.to_string(), 
+        NumberFormat::Scientific => format!("{value:e}"), 
+        NumberFormat::Engineering => engineering(value), 
+    }
+}
+
+/// Rewrites the scientific notation of a finite number such that its exponent is a multiple of 3, by moving
+/// the decimal point of the digits rather than computing with them, which could round them
+fn engineering(value: f64) -> String {
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let mut digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    // the point moves right by up to two digits, which are padded with zeros if there aren't that many
+    let shift = exponent.rem_euclid(3) as usize;
+    while digits.len() < shift + 1 {
+        digits.push('0');
+    }
+    let (integer, fraction) = digits.split_at(shift + 1);
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    match fraction {
+        "" => format!("{sign}{integer}e{}", exponent - shift as i32), 
+        _ => format!("{sign}{integer}.{fraction}e{}", exponent - shift as i32), 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain() {
+        let plain = |value| format_number(value, NumberFormat::Plain);
+        assert_eq!(plain(1.5e6), "1500000");
+        assert_eq!(plain(2.5e-4), "0.00025");
+        assert_eq!(plain(-3.0), "-3");
+        assert_eq!(plain(0.1 + 0.2), "0.30000000000000004");
+    }
+
+    #[test]
+    fn scientific() {
+        let scientific = |value| format_number(value, NumberFormat::Scientific);
+        assert_eq!(scientific(1.5e6), "1.5e6");
+        assert_eq!(scientific(2.5e-4), "2.5e-4");
+        assert_eq!(scientific(-1234.5), "-1.2345e3");
+        assert_eq!(scientific(1.0), "1e0");
+        assert_eq!(scientific(0.0), "0e0");
+        assert_eq!(scientific(f64::INFINITY), "inf");
+    }
+
+    #[test]
+    fn engineering() {
+        let engineering = |value| format_number(value, NumberFormat::Engineering);
+        assert_eq!(engineering(1.5e6), "1.5e6");
+        assert_eq!(engineering(15e6), "15e6");
+        assert_eq!(engineering(150e6), "150e6");
+        assert_eq!(engineering(1.2345e7), "12.345e6");
+        assert_eq!(engineering(2.5e-4), "250e-6");
+        assert_eq!(engineering(-4.7e-8), "-47e-9");
+        assert_eq!(engineering(1.0), "1e0");
+        assert_eq!(engineering(0.0), "0e0");
+        assert_eq!(engineering(-0.5), "-500e-3");
+        assert_eq!(engineering(f64::NEG_INFINITY), "-inf");
+        assert_eq!(engineering(f64::NAN), "NaN");
+    }
+}
@@ -1,86 +1,439 @@
-/// Lexical token that's used for parsing. Contains the value of the token as well as its spacing from the
-/// preceeding token
+use alloc::{
+    collections::VecDeque, 
+    string::String, 
+    vec::Vec, 
+};
+use core::{
+    fmt::{self, Display, Formatter}, 
+    iter::FusedIterator, 
+};
+use crate::{BinOp, UnOp};
+
+/// Lexical token that's used for parsing. Contains the value of the token, its spacing from the preceeding
+/// token, and its span as (start, end) byte offsets into the input string. Numbers also contain their lexeme
 #[derive(Clone, Copy, Debug)]
 pub enum Token<'a> {
-    Number(f64, usize), 
-    Symbol(char, usize), 
-    Word(&'a str, usize), 
+    Number(f64, &'a str, usize, (usize, usize)), 
+    Symbol(&'a str, usize, (usize, usize)), 
+    Word(&'a str, usize, (usize, usize)), 
 }
 
-impl Token<'_> {
+impl<'a> Token<'a> {
+    /// Amount of whitespace between the token and the preceeding one. The first token always has a spacing of
+    /// 0, regardless of any leading whitespace in the input
     pub fn spacing(&self) -> usize {
         match self {
-            Token::Number(_, s) => *s,
-            Token::Symbol(_, s) => *s,
-            Token::Word(_, s)   => *s,
+            Token::Number(_, _, s, _) => *s,
+            Token::Symbol(_, s, _) => *s,
+            Token::Word(_, s, _)   => *s,
+        }
+    }
+
+    /// Byte range `(start, end)` of the token in the input, where `end` is exclusive
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            Token::Number(_, _, _, s) => *s,
+            Token::Symbol(_, _, s) => *s,
+            Token::Word(_, _, s)   => *s,
+        }
+    }
+
+    /// Whether the token is a symbol of a built-in operator, e.g. `+`, `!` or `√`, as opposed to punctuation
+    /// such as parentheses, `,` and `;`
+    pub fn is_operator(&self) -> bool {
+        match self {
+            Token::Symbol("√", ..) => true, 
+            Token::Symbol(symbol, ..) => {
+                BinOp::from_symbol(symbol).is_some() || UnOp::BUILTIN.iter().any(|op| op.name() == *symbol)
+            }
+            _ => false, 
+        }
+    }
+
+    /// Value of the token if it's a number
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Token::Number(number, ..) => Some(*number), 
+            _ => None, 
+        }
+    }
+
+    /// Lexeme of the token if it's a word, e.g. the name of a variable or function
+    pub fn as_word(&self) -> Option<&'a str> {
+        match self {
+            Token::Word(word, ..) => Some(word), 
+            _ => None, 
+        }
+    }
+}
+
+/// Displays the lexeme of the token. The alternate flag prefixes the lexeme with the spacing of the token,
+/// e.g. `1:+` for a `+` preceeded by one space
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}:", self.spacing())?;
+        }
+        match self {
+            Token::Number(_, lexeme, ..) => write!(f, "{lexeme}"), 
+            Token::Symbol(symbol, ..) => write!(f, "{symbol}"), 
+            Token::Word(word, ..) => write!(f, "{word}"), 
+        }
+    }
+}
+
+/// Breakdown of the whitespace preceding a token by kind, as counts of characters. Whitespace on either side
+/// of a comment is counted together. `Token::spacing` is computed from it, weighing tabs and newlines by the
+/// widths in the `LexOptions`, except that the first token always has a spacing of 0
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Whitespace {
+    /// Number of spaces, ` `
+    pub spaces: usize, 
+    /// Number of tabs, `\t`
+    pub tabs: usize, 
+    /// Number of newlines, `\n`
+    pub newlines: usize, 
+    /// Number of any other whitespace characters, e.g. `\r`
+    pub other: usize, 
+}
+
+/// Reasons for which the input can't be tokenized
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexError {
+    /// The lexeme looks like a number but isn't a valid one
+    InvalidNumber(String), 
+    /// A block comment was opened with `/*` but never closed
+    UnterminatedComment, 
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LexError::InvalidNumber(lexeme) => write!(f, "invalid number `{lexeme}`"), 
+            LexError::UnterminatedComment => write!(f, "unterminated block comment"), 
+        }
+    }
+}
+
+impl core::error::Error for LexError {}
+
+/// Options that alter how input is tokenized
+#[derive(Clone, Debug)]
+pub struct LexOptions {
+    /// Amount of spacing that a tab counts as
+    pub tab_width: usize, 
+    /// Amount of spacing that a newline counts as
+    pub newline_width: usize, 
+    /// Maximum spacing of a token, such that any wider gap counts as this much. Unlimited if `None`
+    pub max_spacing: Option<usize>, 
+    /// Character that separates the integer part of a number from its fraction, e.g. `,` for `1,5`. Unless
+    /// it's `.`, it's only part of a number when followed by a digit, such that `max(1, 2)` still has two
+    /// arguments, and a `.` is then an invalid number unless it's the grouping separator
+    pub decimal_separator: char, 
+    /// Character that groups the digits of a number, which is ignored like `_`, e.g. `.` for `1.000,5`. It
+    /// must be in the integer part and followed by exactly three digits, such that `1.5` is invalid rather
+    /// than `15`. Numbers aren't grouped if `None`
+    pub grouping_separator: Option<char>, 
+}
+
+/// Every whitespace character counts as a spacing of 1 by default, with no maximum spacing. Numbers use a
+/// `.` decimal separator and aren't grouped
+impl Default for LexOptions {
+    fn default() -> Self {
+        LexOptions {
+            tab_width: 1, 
+            newline_width: 1, 
+            max_spacing: None, 
+            decimal_separator: '.', 
+            grouping_separator: None, 
         }
     }
 }
 
-/// Token iterator from an input string
+/// Upper bound of the spacing of any token. Spacing is kept strictly below `usize::MAX` so that it never
+/// collides with the precedence that's exceeded by all operators
+const SPACING_LIMIT: usize = usize::MAX - 1;
+
+/// Token iterator from an input string. Iteration never panics: on malformed input the iterator ends, and the
+/// reason is available from `Tokens::last_error`
+#[derive(Clone)]
 pub struct Tokens<'a> {
     /// String being tokenized
     pub string: &'a str, 
-    /// Cached value of the next token, set by `Tokens::peek`. Allows for reading a token without consuming
-    /// it
-    peek: Option<Token<'a>>, 
+    /// Length of the input string, used to compute the byte offset of tokens as `string` is consumed
+    len: usize, 
+    /// Byte offset of the end of the last token yielded
+    end: usize, 
+    /// Cached values of the next tokens along with the whitespace preceding them, filled by
+    /// `Tokens::peek_nth`. Allows for reading tokens without consuming them
+    peeked: VecDeque<(Token<'a>, Whitespace)>, 
+    /// Error encountered while tokenizing. Once set, no more tokens are yielded
+    error: Option<LexError>, 
+    /// Options for how the input is tokenized
+    options: LexOptions, 
 }
 
 impl<'a> Tokens<'a> {
+    /// Creates a token iterator from an input string using the given options
+    pub fn with_options(string: &'a str, options: LexOptions) -> Self {
+        Tokens {
+            string, 
+            len: string.len(), 
+            end: 0, 
+            peeked: VecDeque::new(), 
+            error: None, 
+            options, 
+        }
+    }
+
+    /// Creates a token iterator from an input string using the default options, failing if any of the input
+    /// can't be tokenized. See `Tokens::validate`
+    pub fn try_new(string: &'a str) -> Result<Self, LexError> {
+        let mut tokens = Tokens::from(string);
+        tokens.validate()?;
+        Ok(tokens)
+    }
+
+    /// Tokenizes the rest of the input up front, returning the error that stops tokenization, if any. The
+    /// tokens are kept in the peek cache, such that they're still yielded by `<Tokens as Iterator>::next`
+    /// up to the error. Useful for telling lexing errors apart from parsing errors before parsing
+    pub fn validate(&mut self) -> Result<(), LexError> {
+        while let Some(token) = self.lex() {
+            self.peeked.push_back(token);
+        }
+        match &self.error {
+            Some(err) => Err(err.clone()), 
+            None => Ok(()), 
+        }
+    }
+
+    /// The error that stopped tokenization, if any. Used to tell a lexing error apart from the end of input
+    /// once the iterator yields `None`
+    pub fn last_error(&self) -> Option<&LexError> {
+        self.error.as_ref()
+    }
+
+    /// Copy of the iterator at its current position, including any peeked tokens and error. Tokens borrow
+    /// the input, so this is cheap, and allows for parsing speculatively from one copy and resuming from the
+    /// other if that fails
+    pub fn snapshot(&self) -> Tokens<'a> {
+        self.clone()
+    }
+
     /// Reads the next token and stores it in the peek cache, such that it can still be the next token
     /// yielded by `<Tokens as Iterator>::next`
     pub fn peek(&mut self) -> Option<&Token<'a>> {
-        self.peek = self.peek.or_else(|| self.next());
-        self.peek.as_ref()
+        self.peek_nth(0)
     }
-}
 
-impl<'a> From<&'a str> for Tokens<'a> {
-    fn from(string: &'a str) -> Self {
-        Tokens {
-            string, 
-            peek: None, 
+    /// Reads the token `n` positions ahead, storing it and all tokens before it in the peek cache such that
+    /// they're still yielded in order by `<Tokens as Iterator>::next`
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Token<'a>> {
+        while self.peeked.len() <= n {
+            let token = self.lex()?;
+            self.peeked.push_back(token);
         }
+        self.peeked.get(n).map(|(token, _)| token)
     }
-}
 
-impl<'a> Iterator for Tokens<'a> {
-    type Item = Token<'a>;
+    /// Yields the next token like `<Tokens as Iterator>::next`, along with the breakdown of the whitespace
+    /// preceding it. Useful for diagnosing how mixed whitespace was counted towards the spacing
+    pub fn next_with_whitespace(&mut self) -> Option<(Token<'a>, Whitespace)> {
+        // if tokens have been peeked, consume and return them in order. otherwise, tokenize input as normal
+        let next = self.peeked
+            .pop_front()
+            .or_else(|| self.lex());
+        if let Some((token, _)) = &next {
+            self.end = token.span().1;
+        }
+        next
+    }
 
-    /// Removes lexemes from the front of string in chunks of one token each
-    fn next(&mut self) -> Option<Self::Item> {
-        // if a token has been peeked, consume and return it. otherwise, tokenize input as normal
-        if let Some(peek) = self.peek.take() {
-            return Some(peek)
+    /// Byte offset in the input just past the last token that's been yielded, or 0 if none has. Peeked tokens
+    /// don't count, since they're yet to be yielded, such that the input from this offset is what remains
+    pub fn offset(&self) -> usize {
+        self.end
+    }
+
+    /// Removes a lexeme from the front of string and produces its token, along with the whitespace preceding
+    /// it
+    fn lex(&mut self) -> Option<(Token<'a>, Whitespace)> {
+        if self.error.is_some() {
+            return None
         }
 
-        // removes all leading spaces, later storing the length of it inside the token
-        let spacing = gobble(Category::Whitespace, &mut self.string);
-        let spacing = spacing.chars().count();
+        // removes all leading spaces and comments, later storing the length of the spaces inside the token.
+        // comments are ignored entirely, such that only the whitespace around them counts towards the
+        // spacing. whitespace at the very start of the input doesn't separate the token from anything, so
+        // it's not counted
+        let is_first = self.string.len() == self.len;
+        let mut spacing: usize = 0;
+        let mut whitespace = Whitespace::default();
+        loop {
+            for c in gobble(Category::Whitespace, &mut self.string).chars() {
+                let (count, width) = match c {
+                    ' ' => (&mut whitespace.spaces, 1), 
+                    '\t' => (&mut whitespace.tabs, self.options.tab_width), 
+                    '\n' => (&mut whitespace.newlines, self.options.newline_width), 
+                    _ => (&mut whitespace.other, 1), 
+                };
+                *count += 1;
+                spacing = spacing.saturating_add(width);
+            }
+            match gobble_comment(&mut self.string) {
+                Ok(true) => continue, 
+                Ok(false) => break, 
+                Err(err) => {
+                    self.error = Some(err);
+                    return None
+                }
+            }
+        }
+        let max_spacing = self.options.max_spacing.map_or(SPACING_LIMIT, |max| max.min(SPACING_LIMIT));
+        let spacing = if is_first { 0 } else { spacing.min(max_spacing) };
 
-        // read the first character in the input and produce a token based on what type it is
+        // read the first character in the input and produce a token based on what type it is. the span is
+        // computed from how much of the input has been consumed before and after the lexeme
         let first = self.string.chars().nth(0)?;
+        let start = self.len - self.string.len();
+        let span = |string: &str| (start, self.len - string.len());
         let token = match Category::from(first) {
             Category::Letter => {
                 let lexeme = gobble(Category::Letter, &mut self.string);
-                Token::Word(lexeme, spacing)
+                Token::Word(lexeme, spacing, span(self.string))
             }
             Category::Digit => {
-                let lexeme = gobble(Category::Digit, &mut self.string);
-                let number = lexeme.parse().expect("Invalid floating-point number");
-                Token::Number(number, spacing)
+                let lexeme = gobble_number(&mut self.string, &self.options);
+                let Some(number) = parse_number(lexeme, &self.options) else {
+                    self.error = Some(LexError::InvalidNumber(lexeme.into()));
+                    return None
+                };
+                Token::Number(number, lexeme, spacing, span(self.string))
             }
             Category::Symbol => {
-                self.string = &self.string[1..];
-                Token::Symbol(first, spacing)
+                let lexeme = gobble_symbol(&mut self.string);
+                let symbol = SYMBOL_ALIASES.iter()
+                    .find(|(alias, _)| *alias == lexeme)
+                    .map_or(lexeme, |(_, symbol)| symbol);
+                Token::Symbol(symbol, spacing, span(self.string))
             }
             Category::Whitespace => unreachable!("All leading spaces are removed by `gobble`"), 
         };
-        Some(token)
+        Some((token, whitespace))
+    }
+}
+
+impl<'a> From<&'a str> for Tokens<'a> {
+    fn from(string: &'a str) -> Self {
+        Tokens::with_options(string, LexOptions::default())
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    /// Removes lexemes from the front of string in chunks of one token each
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_whitespace().map(|(token, _)| token)
+    }
+}
+
+/// Once the iterator has ended, either at the end of input or at an error, it keeps yielding `None`
+impl FusedIterator for Tokens<'_> {}
+
+/// Tokenizes the entire input string. Tokenization stops at the first lexing error, if any; use `Tokens` to
+/// detect it
+pub fn lex(string: &str) -> Vec<Token<'_>> {
+    Tokens::from(string).collect()
+}
+
+/// Push-style tokenizer for input that arrives in chunks, e.g. from an editor. Chunks are appended with
+/// `StreamingLexer::push_str`, and `StreamingLexer::next_token` yields each token once it's complete, i.e.
+/// once more input follows it or the input is finished. A lexeme that's split across chunks, e.g. `12` pushed
+/// as `1` and `2`, is therefore yielded whole. The tokens are identical to those of `Tokens` over the
+/// concatenated input, including their spans, which is why all input is kept for the lifetime of the lexer
+///
+/// ```
+/// # use space_precedence_parser::lexer::StreamingLexer;
+/// let mut lexer = StreamingLexer::new();
+/// lexer.push_str("12 +");
+/// assert_eq!(lexer.next_token().unwrap().map(|token| token.to_string()), Some("12".into()));
+/// // the `+` could still become part of a longer symbol
+/// assert!(lexer.next_token().unwrap().is_none());
+/// lexer.push_str(" 3");
+/// lexer.finish();
+/// assert_eq!(lexer.next_token().unwrap().map(|token| token.to_string()), Some("+".into()));
+/// assert_eq!(lexer.next_token().unwrap().map(|token| token.to_string()), Some("3".into()));
+/// assert!(lexer.next_token().unwrap().is_none());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StreamingLexer {
+    /// All input pushed so far
+    buffer: String, 
+    /// Byte offset into the buffer up to which tokens have been yielded
+    offset: usize, 
+    /// Whether all input has been pushed, such that a lexeme at the end of the buffer is complete
+    finished: bool, 
+    /// Options for how the input is tokenized
+    options: LexOptions, 
+}
+
+impl StreamingLexer {
+    /// Creates a lexer without any input using the default options
+    pub fn new() -> Self {
+        StreamingLexer::default()
+    }
+
+    /// Creates a lexer without any input using the given options
+    pub fn with_options(options: LexOptions) -> Self {
+        StreamingLexer {
+            options, 
+            ..StreamingLexer::default()
+        }
+    }
+
+    /// Appends a chunk to the input. Has no effect once the input is finished
+    pub fn push_str(&mut self, chunk: &str) {
+        if !self.finished {
+            self.buffer.push_str(chunk);
+        }
+    }
+
+    /// Marks the end of the input, such that the last lexeme is complete
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Reads the next complete token. Returns `Ok(None)` if more input is needed to complete it, or at the
+    /// end of finished input. A lexing error is only returned once it can't be resolved by more input, e.g.
+    /// `1e` may still become `1e5`, and is returned again by every call thereafter
+    pub fn next_token(&mut self) -> Result<Option<Token<'_>>, LexError> {
+        let mut tokens = Tokens {
+            string: &self.buffer[self.offset..], 
+            len: self.buffer.len(), 
+            end: self.offset, 
+            peeked: VecDeque::new(), 
+            error: None, 
+            options: self.options.clone(), 
+        };
+        let token = tokens.lex().map(|(token, _)| token);
+        // the lexeme, comment or whitespace that reaches the end of the buffer may continue in the next
+        // chunk, which may also close an open block comment
+        let unterminated = tokens.error == Some(LexError::UnterminatedComment);
+        if (tokens.string.is_empty() || unterminated) && !self.finished {
+            return Ok(None)
+        }
+        if let Some(err) = tokens.error {
+            return Err(err)
+        }
+        self.offset = self.buffer.len() - tokens.string.len();
+        Ok(token)
     }
 }
 
-/// Utility to store the type of a character
+/// Utility to store the type of a character. Any character that isn't a letter, digit or whitespace is a
+/// symbol, including non-ASCII mathematical symbols such as `×`, whereas letters include non-ASCII ones
+/// such as `π`
 #[derive(PartialEq)]
 enum Category {
     Letter, 
@@ -112,3 +465,562 @@ fn gobble<'a>(category: Category, string: &mut &'a str) -> &'a str {
     *string = rest;
     lexeme
 }
+
+/// Utility that consumes a comment from the front of the string, returning whether there was one. Line
+/// comments run from `#` up to the end of the line, and block comments from `/*` up to and including `*/`
+fn gobble_comment(string: &mut &str) -> Result<bool, LexError> {
+    if let Some(comment) = string.strip_prefix('#') {
+        *string = &comment[comment.find('\n').unwrap_or(comment.len())..];
+        Ok(true)
+    } else if let Some(comment) = string.strip_prefix("/*") {
+        let end = comment.find("*/").ok_or(LexError::UnterminatedComment)?;
+        *string = &comment[end + 2..];
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Symbols that span multiple characters. All other symbols are single characters, such that e.g. `1+-2` is
+/// split into `+` and `-` rather than being read as one symbol. Note that `//` is floor division rather than
+/// a comment, since line comments start with `#`
+const MULTI_CHAR_SYMBOLS: &[&str] = &["<=", ">=", "<<", ">>", "**", "//"];
+
+/// Symbols that are read as the ASCII symbol of the same operator, such that e.g. `2 × 3` is tokenized like
+/// `2 * 3`, and Python's `2 ** 3` like `2 ^ 3`. Tokens and ASTs therefore always display the canonical
+/// symbol, while the span covers the original
+const SYMBOL_ALIASES: &[(&str, &str)] = &[
+    ("×", "*"), 
+    ("÷", "/"), 
+    ("−", "-"), 
+    ("**", "^"), 
+];
+
+/// Utility that consumes a symbol from the front of the string, preferring the longest matching
+/// multi-character symbol. Other symbols are a single character, which may span multiple bytes
+fn gobble_symbol<'a>(string: &mut &'a str) -> &'a str {
+    let len = MULTI_CHAR_SYMBOLS.iter()
+        .filter(|symbol| string.starts_with(*symbol))
+        .map(|symbol| symbol.len())
+        .max()
+        .or_else(|| string.chars().next().map(char::len_utf8))
+        .unwrap_or(0);
+    let (lexeme, rest) = string.split_at(len);
+    *string = rest;
+    lexeme
+}
+
+/// Prefixes of integer literals in other bases than 10, along with their radix, e.g. `0xff` for 255
+const RADIX_PREFIXES: &[(&str, u32)] = &[
+    ("0x", 16), 
+    ("0b", 2), 
+    ("0o", 8), 
+];
+
+/// Utility that consumes a number from the front of the string. This is a run of digits and digit separators
+/// optionally followed by an exponent, which is an `e` or `E`, an optional sign, and another run of digits.
+/// A number with a radix prefix is instead the prefix followed by a run of letters, digits and separators,
+/// such that any malformed digits are part of the lexeme, e.g. `0xZ`. The decimal and grouping separators of
+/// the options are part of the run when followed by a digit. Points are always part of the run, such that
+/// malformed numbers such as `1..2` and `.` are a single lexeme that `parse_number` rejects
+fn gobble_number<'a>(string: &mut &'a str, options: &LexOptions) -> &'a str {
+    let start = *string;
+    if let Some(digits) = RADIX_PREFIXES.iter().find_map(|(prefix, _)| string.strip_prefix(prefix)) {
+        let len = digits
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(digits.len());
+        *string = &digits[len..];
+        return &start[..start.len() - string.len()]
+    }
+    let is_separator = |c| c == options.decimal_separator || Some(c) == options.grouping_separator;
+    let mantissa = string
+        .char_indices()
+        .find(|&(i, c)| {
+            let is_digit = Category::from(c) == Category::Digit || c == '_';
+            let is_separator = is_separator(c)
+                && string[i + c.len_utf8()..].starts_with(|c: char| c.is_ascii_digit());
+            !is_digit && !is_separator
+        })
+        .map_or(string.len(), |(i, _)| i);
+    *string = &string[mantissa..];
+    if let Some(exponent) = string.strip_prefix(['e', 'E']) {
+        *string = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let digits = string
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(string.len());
+        *string = &string[digits..];
+    }
+    &start[..start.len() - string.len()]
+}
+
+/// Utility that computes the value of a number lexeme. Underscores are allowed as digit separators, e.g.
+/// `1_000`, but only between two digits. Numbers with a radix prefix are integers, e.g. `0b1010`, which
+/// need at least one digit of their base. Decimal numbers are normalized to a `.` decimal separator without
+/// any grouping before being parsed. Their decimal separator may lead or trail the digits, e.g. `.5` and
+/// `1.`, but there must be at least one digit, so `.` is invalid
+fn parse_number(lexeme: &str, options: &LexOptions) -> Option<f64> {
+    let prefixed = RADIX_PREFIXES.iter()
+        .find_map(|&(prefix, radix)| Some((lexeme.strip_prefix(prefix)?, radix)));
+    if let Some((digits, radix)) = prefixed {
+        if !is_separated(digits, radix) {
+            return None
+        }
+        return u128::from_str_radix(&digits.replace('_', ""), radix)
+            .ok()
+            .map(|value| value as f64)
+    }
+    let mut normalized = String::with_capacity(lexeme.len());
+    for (i, c) in lexeme.char_indices() {
+        if Some(c) == options.grouping_separator {
+            // only the integer part is grouped, in groups of three digits
+            let group = lexeme[i + c.len_utf8()..].chars().take_while(|c| c.is_ascii_digit()).count();
+            let is_grouped = !normalized.contains('.')
+                && lexeme[..i].ends_with(|c: char| c.is_ascii_digit())
+                && group == 3;
+            if !is_grouped {
+                return None
+            }
+        } else if c == options.decimal_separator {
+            normalized.push('.');
+        } else if c == '.' {
+            return None
+        } else {
+            normalized.push(c);
+        }
+    }
+    if !is_separated(&normalized, 10) {
+        return None
+    }
+    normalized.replace('_', "").parse().ok()
+}
+
+/// Whether a valid number lexeme is an integer rather than a float, which is the case unless it's written
+/// with a decimal separator or an exponent. Numbers with a radix prefix are always integers
+pub(crate) fn is_integer(lexeme: &str, options: &LexOptions) -> bool {
+    if RADIX_PREFIXES.iter().any(|(prefix, _)| lexeme.starts_with(prefix)) {
+        return true
+    }
+    !lexeme.contains([options.decimal_separator, 'e', 'E'])
+}
+
+/// Whether every underscore in the digits is between two digits of the given base
+fn is_separated(digits: &str, radix: u32) -> bool {
+    let bytes = digits.as_bytes();
+    let is_digit = |i: Option<usize>| i
+        .and_then(|i| bytes.get(i))
+        .is_some_and(|&byte| char::from(byte).is_digit(radix));
+    (0..bytes.len())
+        .filter(|&i| bytes[i] == b'_')
+        .all(|i| is_digit(i.checked_sub(1)) && is_digit(Some(i + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let input = "12 + sqrt 3";
+        let lexemes: Vec<_> = Tokens::from(input)
+            .map(|token| {
+                let (start, end) = token.span();
+                &input[start..end]
+            })
+            .collect();
+        assert_eq!(lexemes, ["12", "+", "sqrt", "3"]);
+    }
+
+    #[test]
+    fn offset() {
+        let mut tokens = Tokens::from("12 +  3 ");
+        assert_eq!(tokens.offset(), 0);
+        tokens.next();
+        assert_eq!(tokens.offset(), 2);
+        tokens.peek_nth(1);
+        assert_eq!(tokens.offset(), 2);
+        tokens.next();
+        assert_eq!(tokens.offset(), 4);
+        assert_eq!(tokens.by_ref().count(), 1);
+        assert_eq!(tokens.offset(), 7);
+    }
+
+    #[test]
+    fn lex() {
+        let tokens = super::lex("1* 2+ 3");
+        let lexemes: Vec<_> = tokens.iter()
+            .map(|token| (token.to_string(), token.spacing()))
+            .collect();
+        let expected = [("1", 0), ("*", 0), ("2", 1), ("+", 0), ("3", 1)].map(|(x, s)| (x.to_string(), s));
+        assert_eq!(lexemes, expected);
+
+        let rendered: Vec<_> = tokens.iter()
+            .map(|token| format!("{token:#}"))
+            .collect();
+        assert_eq!(rendered.join(" "), "0:1 0:* 1:2 0:+ 1:3");
+    }
+
+    #[test]
+    fn helpers() {
+        let tokens = super::lex("x = 2.5 * (sqrt 4)! ;");
+        let operators: Vec<_> = tokens.iter()
+            .filter(|token| token.is_operator())
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(operators, ["*", "!"]);
+        assert!(super::lex("1 × 2")[1].is_operator());
+        assert!(super::lex("√2")[0].is_operator());
+
+        let numbers: Vec<_> = tokens.iter().filter_map(Token::as_number).collect();
+        assert_eq!(numbers, [2.5, 4.0]);
+        let words: Vec<_> = tokens.iter().filter_map(Token::as_word).collect();
+        assert_eq!(words, ["x", "sqrt"]);
+    }
+
+    #[test]
+    fn whitespace() {
+        let options = LexOptions {
+            tab_width: 4, 
+            ..LexOptions::default()
+        };
+        let mut tokens = Tokens::with_options("\n1 \t + /* */\n 2", options);
+        let (token, whitespace) = tokens.next_with_whitespace().unwrap();
+        assert_eq!((token.spacing(), whitespace), (0, Whitespace { newlines: 1, ..Whitespace::default() }));
+        let (token, whitespace) = tokens.next_with_whitespace().unwrap();
+        let expected = Whitespace { spaces: 2, tabs: 1, ..Whitespace::default() };
+        assert_eq!((token.spacing(), whitespace), (6, expected));
+
+        // whitespace is kept along with peeked tokens
+        tokens.peek();
+        let (token, whitespace) = tokens.next_with_whitespace().unwrap();
+        let expected = Whitespace { spaces: 2, newlines: 1, ..Whitespace::default() };
+        assert_eq!((token.to_string(), token.spacing(), whitespace), ("2".into(), 3, expected));
+        assert!(tokens.next_with_whitespace().is_none());
+    }
+
+    #[test]
+    fn spacing() {
+        let spacings = |input, options| {
+            Tokens::with_options(input, options)
+                .map(|token| token.spacing())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spacings("1 \t\n 2", LexOptions::default()), [0, 4]);
+
+        let options = LexOptions {
+            tab_width: 4, 
+            newline_width: 10, 
+            ..LexOptions::default()
+        };
+        assert_eq!(spacings("1 \t\n 2", options.clone()), [0, 16]);
+        assert_eq!(spacings("\t1\n\n2", options), [0, 20]);
+        assert_eq!(spacings("   1  2", LexOptions::default()), [0, 2]);
+
+        let options = LexOptions {
+            max_spacing: Some(3), 
+            ..LexOptions::default()
+        };
+        assert_eq!(spacings("1  2     3   4", options), [0, 2, 3, 3]);
+
+        let options = LexOptions {
+            tab_width: usize::MAX, 
+            ..LexOptions::default()
+        };
+        assert_eq!(spacings("1\t\t2", options), [0, usize::MAX - 1]);
+    }
+
+    #[test]
+    fn comments() {
+        let tokens = |input| {
+            Tokens::from(input)
+                .map(|token| format!("{token:#}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tokens("1 + 2 # ignored"), ["0:1", "1:+", "1:2"]);
+        assert_eq!(tokens("1 + # ignored\n2"), ["0:1", "1:+", "2:2"]);
+        assert_eq!(tokens("1 /* x */ + 2"), ["0:1", "2:+", "1:2"]);
+        assert_eq!(tokens("1/* x */+/**/2"), ["0:1", "0:+", "0:2"]);
+        assert_eq!(tokens("/* a */ # b\n 1 /* c */"), ["0:1"]);
+        assert_eq!(tokens("1 /* x # y */ 2"), ["0:1", "2:2"]);
+        assert_eq!(tokens("1 /* /* */ 2"), ["0:1", "2:2"]);
+        assert_eq!(tokens("#"), Vec::<String>::new());
+
+        for input in ["1 /* x", "/*/", "1 + /* */ /*"] {
+            let mut tokens = Tokens::from(input);
+            while tokens.next().is_some() {}
+            assert_eq!(tokens.last_error(), Some(&LexError::UnterminatedComment));
+        }
+    }
+
+    #[test]
+    fn digit_separators() {
+        let number = |input| match Tokens::from(input).collect::<Vec<_>>()[..] {
+            [Token::Number(number, ..)] => number, 
+            _ => panic!("Expected a single number"), 
+        };
+        assert_eq!(number("1_000"), 1000.0);
+        assert_eq!(number("1_000.5"), 1000.5);
+        assert_eq!(number("1.234_5"), 1.2345);
+        assert_eq!(number("1_0e-1"), 1.0);
+
+        for input in ["1_", "1__000", "1_.5", "1._5"] {
+            let mut tokens = Tokens::from(input);
+            assert!(tokens.next().is_none());
+            assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber(input.into())));
+        }
+        let mut tokens = Tokens::from("_5");
+        assert!(matches!(tokens.next(), Some(Token::Word("_", ..))));
+        assert!(matches!(tokens.next(), Some(Token::Number(5.0, ..))));
+    }
+
+    #[test]
+    fn locales() {
+        let options = LexOptions {
+            decimal_separator: ',', 
+            grouping_separator: Some('.'), 
+            ..LexOptions::default()
+        };
+        let numbers = |input| Tokens::with_options(input, options.clone())
+            .map(|token| token.as_number())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers("1.000,5"), [Some(1000.5)]);
+        assert_eq!(numbers("1.000.000"), [Some(1e6)]);
+        assert_eq!(numbers("0,25e2"), [Some(25.0)]);
+        assert_eq!(numbers("1,5 , 2"), [Some(1.5), None, Some(2.0)]);
+        assert_eq!(numbers("1, 2"), [Some(1.0), None, Some(2.0)]);
+
+        assert_eq!(numbers("12.345.678,9"), [Some(12345678.9)]);
+        for input in ["1..000", "1,000.5", "1._000", "1.5", "1.23", "1.0000", "1.000.00", "1.00,5"] {
+            let mut tokens = Tokens::with_options(input, options.clone());
+            assert!(tokens.next().is_none());
+            assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber(input.into())));
+        }
+
+        // without grouping, a `.` can't be part of a number with another decimal separator
+        let options = LexOptions {
+            decimal_separator: ',', 
+            ..LexOptions::default()
+        };
+        let mut tokens = Tokens::with_options("1,5 + 1.5", options);
+        assert_eq!(tokens.next().and_then(|token| token.as_number()), Some(1.5));
+        tokens.next();
+        assert!(tokens.next().is_none());
+        assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber("1.5".into())));
+        assert_eq!(super::lex("1,5")[0].as_number(), Some(1.0));
+    }
+
+    #[test]
+    fn radix_prefixes() {
+        let number = |input| match Tokens::from(input).collect::<Vec<_>>()[..] {
+            [Token::Number(number, ..)] => number, 
+            _ => panic!("Expected a single number"), 
+        };
+        assert_eq!(number("0xff"), 255.0);
+        assert_eq!(number("0xFF"), 255.0);
+        assert_eq!(number("0b1010"), 10.0);
+        assert_eq!(number("0o17"), 15.0);
+        assert_eq!(number("0b1111_0000"), 240.0);
+        assert_eq!(number("0x0"), 0.0);
+
+        for input in ["0x", "0xZ", "0b102", "0o8", "0x_f", "0xf_"] {
+            let mut tokens = Tokens::from(input);
+            assert!(tokens.next().is_none());
+            assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber(input.into())));
+        }
+        let lexemes: Vec<_> = super::lex("0xff+1").iter().map(|token| token.to_string()).collect();
+        assert_eq!(lexemes, ["0xff", "+", "1"]);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut tokens = Tokens::from("1 + 2");
+        tokens.next();
+        tokens.peek();
+        let snapshot = tokens.snapshot();
+        assert_eq!(tokens.map(|token| token.to_string()).collect::<Vec<_>>(), ["+", "2"]);
+        assert_eq!(snapshot.map(|token| token.to_string()).collect::<Vec<_>>(), ["+", "2"]);
+
+        let mut tokens = Tokens::from("1 0x");
+        let snapshot = tokens.snapshot();
+        assert_eq!(tokens.validate(), Err(LexError::InvalidNumber("0x".into())));
+        assert_eq!(snapshot.last_error(), None);
+    }
+
+    #[test]
+    fn validate() {
+        let tokens = Tokens::try_new("1 + 2 # comment").unwrap();
+        let lexemes: Vec<_> = tokens.map(|token| token.to_string()).collect();
+        assert_eq!(lexemes, ["1", "+", "2"]);
+        assert!(Tokens::try_new("").unwrap().next().is_none());
+
+        assert_eq!(Tokens::try_new("1 + 2.3.4").err(), Some(LexError::InvalidNumber("2.3.4".into())));
+        assert_eq!(Tokens::try_new("1 /* 2").err(), Some(LexError::UnterminatedComment));
+
+        // validating leaves the tokens before the error to be yielded, even after some have been consumed
+        let mut tokens = Tokens::from("1 + 2 * 0xZ");
+        assert!(matches!(tokens.next(), Some(Token::Number(1.0, ..))));
+        assert_eq!(tokens.validate(), Err(LexError::InvalidNumber("0xZ".into())));
+        assert_eq!(tokens.validate(), Err(LexError::InvalidNumber("0xZ".into())));
+        let lexemes: Vec<_> = tokens.map(|token| token.to_string()).collect();
+        assert_eq!(lexemes, ["+", "2", "*"]);
+    }
+
+    #[test]
+    fn decimal_points() {
+        let number = |input| {
+            let mut tokens = Tokens::from(input);
+            match tokens.next() {
+                Some(Token::Number(value, lexeme, ..)) => Ok((value, lexeme)), 
+                _ => Err(tokens.last_error().cloned()), 
+            }
+        };
+        assert_eq!(number(".5"), Ok((0.5, ".5")));
+        assert_eq!(number("1."), Ok((1.0, "1.")));
+        assert_eq!(number("1.e2"), Ok((100.0, "1.e2")));
+        assert!(!is_integer("1.", &LexOptions::default()));
+        for input in [".", "..", "1..2", "..5", ".e5", "1.2.", "._5"] {
+            assert_eq!(number(input), Err(Some(LexError::InvalidNumber(input.into()))));
+        }
+
+        // the point ends the lexeme along with the rest of the number
+        let lexemes: Vec<_> = Tokens::from("1. + .5").map(|token| token.to_string()).collect();
+        assert_eq!(lexemes, ["1.", "+", ".5"]);
+        let mut tokens = Tokens::from("2 * . + 1");
+        assert_eq!(tokens.by_ref().count(), 2);
+        assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber(".".into())));
+    }
+
+    #[test]
+    fn errors() {
+        let mut tokens = Tokens::from("1 + 2.3.4 * 5");
+        assert!(matches!(tokens.peek_nth(1), Some(Token::Symbol("+", ..))));
+        assert!(tokens.peek_nth(2).is_none());
+        assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber("2.3.4".into())));
+        let lexemes: Vec<_> = tokens.by_ref().map(|token| token.to_string()).collect();
+        assert_eq!(lexemes, ["1", "+"]);
+        assert!(tokens.next().is_none());
+        assert!(tokens.peek().is_none());
+        assert_eq!(tokens.last_error(), Some(&LexError::InvalidNumber("2.3.4".into())));
+
+        let mut tokens = Tokens::from("1 + 2");
+        assert_eq!(tokens.by_ref().count(), 3);
+        assert!(tokens.next().is_none());
+        assert_eq!(tokens.last_error(), None);
+
+        // every combination of a few troublesome characters is tokenized without panicking
+        let chars = ["1", ".", "e", "_", "-", "×", "π", "/", "*", "#", " ", "\n", "é", "<", "="];
+        for a in chars {
+            for b in chars {
+                for c in chars {
+                    let input = format!("{a}{b}{c}");
+                    let mut tokens = Tokens::from(input.as_str());
+                    while tokens.next().is_some() {}
+                    assert!(tokens.next().is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn peek_nth() {
+        let mut tokens = Tokens::from("1 + sqrt 2");
+        assert!(matches!(tokens.peek_nth(2), Some(Token::Word("sqrt", ..))));
+        assert!(matches!(tokens.peek(), Some(Token::Number(1.0, ..))));
+        assert!(matches!(tokens.peek_nth(1), Some(Token::Symbol("+", ..))));
+        assert!(tokens.peek_nth(4).is_none());
+        assert!(matches!(tokens.peek_nth(3), Some(Token::Number(2.0, ..))));
+
+        let peeked: Vec<_> = (0..4).map(|n| tokens.peek_nth(n).unwrap().to_string()).collect();
+        let consumed: Vec<_> = tokens.map(|token| token.to_string()).collect();
+        assert_eq!(peeked, consumed);
+        assert_eq!(consumed, ["1", "+", "sqrt", "2"]);
+    }
+
+    #[test]
+    fn streaming() {
+        let render = |token: Token| format!("{token:#}@{:?}", token.span());
+        let stream = |chunks: &[&str]| {
+            let mut lexer = StreamingLexer::new();
+            let mut rendered = Vec::new();
+            for chunk in chunks {
+                lexer.push_str(chunk);
+                while let Some(token) = lexer.next_token().unwrap() {
+                    rendered.push(render(token));
+                }
+            }
+            lexer.finish();
+            while let Some(token) = lexer.next_token().unwrap() {
+                rendered.push(render(token));
+            }
+            rendered
+        };
+        let expected = |input| Tokens::from(input).map(render).collect::<Vec<_>>();
+        assert_eq!(stream(&["12 +", " 3"]), expected("12 + 3"));
+        assert_eq!(stream(&["1", "2", " ", "+", " ", "3"]), expected("12 + 3"));
+
+        // the input is split at every character boundary
+        let input = "  1.5e3 <= x /* c */ + sqrt 2 # done\n× 0xf";
+        for (i, _) in input.char_indices() {
+            assert_eq!(stream(&[&input[..i], &input[i..]]), expected(input), "split at {i}");
+        }
+
+        let mut lexer = StreamingLexer::new();
+        lexer.push_str("1 + 1e");
+        assert!(matches!(lexer.next_token(), Ok(Some(Token::Number(1.0, ..)))));
+        assert!(matches!(lexer.next_token(), Ok(Some(Token::Symbol("+", ..)))));
+        assert!(matches!(lexer.next_token(), Ok(None)));
+        lexer.push_str("x");
+        assert_eq!(lexer.next_token().unwrap_err(), LexError::InvalidNumber("1e".into()));
+        assert_eq!(lexer.next_token().unwrap_err(), LexError::InvalidNumber("1e".into()));
+
+        let mut lexer = StreamingLexer::new();
+        lexer.push_str("1 /* 2");
+        assert!(matches!(lexer.next_token(), Ok(Some(Token::Number(1.0, ..)))));
+        assert!(matches!(lexer.next_token(), Ok(None)));
+        lexer.finish();
+        assert_eq!(lexer.next_token().unwrap_err(), LexError::UnterminatedComment);
+    }
+
+    #[test]
+    fn symbols() {
+        let symbols = |input| {
+            Tokens::from(input)
+                .map(|token| token.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(symbols("<= >= < > ="), ["<=", ">=", "<", ">", "="]);
+        assert_eq!(symbols("1<=2"), ["1", "<=", "2"]);
+        assert_eq!(symbols("1+-2"), ["1", "+", "-", "2"]);
+        assert_eq!(symbols("< ="), ["<", "="]);
+        assert_eq!(symbols("(-(1))"), ["(", "-", "(", "1", ")", ")"]);
+        assert_eq!(symbols("2×3 ÷ −1"), ["2", "*", "3", "/", "-", "1"]);
+        assert_eq!(symbols("2**3 * *3 ***"), ["2", "^", "3", "*", "*", "3", "^", "*"]);
+        assert_eq!(symbols("≤≥→"), ["≤", "≥", "→"]);
+        assert_eq!(symbols("π√x"), ["π", "√", "x"]);
+        assert_eq!(symbols("7//2 / /"), ["7", "//", "2", "/", "/"]);
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let numbers = |input| {
+            Tokens::from(input)
+                .map(|token| match token {
+                    Token::Number(number, ..) => number, 
+                    _ => panic!("Expected a number"), 
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(numbers("1e3"), [1e3]);
+        assert_eq!(numbers("2.5e-3"), [2.5e-3]);
+        assert_eq!(numbers("1E+2"), [1e2]);
+        assert_eq!(numbers("1.5e10 3"), [1.5e10, 3.0]);
+
+        for input in ["1e", "1e+", "2E-", "1e+ 3", "1.2.3", ".", "..", "1.e"] {
+            let mut tokens = Tokens::from(input);
+            assert!(tokens.next().is_none());
+            assert!(matches!(tokens.last_error(), Some(LexError::InvalidNumber(_))));
+        }
+        let mut tokens = Tokens::from("sqrt2e3");
+        assert!(matches!(tokens.next(), Some(Token::Word("sqrt", ..))));
+        assert!(matches!(tokens.next(), Some(Token::Number(2e3, ..))));
+    }
+}
@@ -0,0 +1,55 @@
+//! A parser that uses spacing to dictate operator precedence, wherein spaces can be seen as implicit
+//! parentheses. See the README for examples. Only `core` and `alloc` are required when the default `std`
+//! feature is disabled
+// tests always link `std`, but the library itself still avoids it without the feature
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+pub mod lexer;
+mod ast;
+mod diff;
+mod eval;
+mod format;
+mod numeric;
+mod parser;
+mod simplify;
+mod typecheck;
+
+pub use ast::{Ast, BinOp, Number, NumberKind, Parenthesized, UnOp};
+pub use diff::structural_diff;
+pub use eval::{
+    eval, 
+    eval_in, 
+    eval_int, 
+    eval_int_with, 
+    eval_numeric, 
+    eval_numeric_with, 
+    eval_with, 
+    eval_with_division, 
+    Env, 
+    EvalError, 
+    IntDivision, 
+    ZeroDivision, 
+};
+pub use format::{format_number, NumberFormat};
+pub use lexer::lex;
+pub use numeric::Numeric;
+pub use parser::{
+    compare_operators, 
+    parse, 
+    parse_prefix, 
+    parse_prefix_with, 
+    parse_traced, 
+    parse_with, 
+    render_error, 
+    Associativity, 
+    Asymmetry, 
+    Chaining, 
+    ParseError, 
+    ParseOptions, 
+    Precedence, 
+    PrecedenceOrder, 
+    Trace, 
+};
+pub use simplify::{simplify, simplify_negations};
+pub use typecheck::{typecheck, TypeError};
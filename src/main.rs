@@ -1,161 +1,227 @@
 use std::{
-    fmt::{self, Display, Formatter}, 
+    cell::RefCell, 
     env, 
-    cmp::Ordering, 
+    io::{self, BufRead, Read, Write}, 
+    process, 
+};
+use space_precedence_parser::{
+    eval, 
+    format_number, 
+    parse_traced, 
+    render_error, 
+    NumberFormat, 
+    ParseOptions, 
+    Trace, 
 };
-use lexer::*;
-
-mod lexer;
 
-/// The AST structure being parsed
-#[derive(Debug)]
-enum Ast {
-    /// Literal numerical value
-    Literal(f64), 
-    /// Unary operation
-    Unary(String, Box<Ast>), 
-    /// Binary operation
-    Binary(String, Box<(Ast, Ast)>), 
+/// Output settings given as command line flags
+#[derive(Clone, Copy, Default)]
+struct Flags {
+    /// Print the AST as JSON rather than fully parenthesized
+    json: bool, 
+    /// Print each precedence decision made by the parser to stderr
+    verbose: bool, 
+    /// Print only the value of the expression, failing if it can't be evaluated
+    eval: bool, 
+    /// Notation that values are printed in
+    format: NumberFormat, 
 }
 
-impl Display for Ast {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self {
-            Ast::Literal(value) => write!(f, "{value}"),
-            Ast::Unary(op, x) => write!(f, "({op} {x})"), 
-            Ast::Binary(op, args) => {
-                let x = &args.0;
-                let y = &args.1;
-                write!(f, "({x} {op} {y})")
-            }, 
+/// Parses and evaluates an expression, printing the AST and its value, or only the value if `flags.eval` is
+/// set. Returns whether it succeeded, where a failed evaluation only counts as a failure with `flags.eval`
+fn run(input: &str, flags: Flags, stdout: &mut impl Write, stderr: &mut impl Write) -> io::Result<bool> {
+    let traces = RefCell::new(Vec::new());
+    let trace = |trace: &Trace| if flags.verbose {
+        traces.borrow_mut().push(trace.to_string());
+    };
+    let result = parse_traced(input, &ParseOptions::default(), &trace);
+    for trace in traces.into_inner() {
+        writeln!(stderr, "{trace}")?;
+    }
+    let expr = match result {
+        Ok(expr) => expr, 
+        Err(err) => {
+            writeln!(stderr, "{}", render_error(input, &err))?;
+            return Ok(false)
+        }
+    };
+    if flags.eval {
+        return match eval(&expr) {
+            Ok(value) => writeln!(stdout, "{}", format_number(value, flags.format)).map(|_| true), 
+            Err(err) => writeln!(stderr, "error: {err}").map(|_| false), 
         }
     }
+    if flags.json {
+        writeln!(stdout, "{}", expr.to_json())?;
+    } else {
+        writeln!(stdout, "{expr}")?;
+    }
+    match eval(&expr) {
+        Ok(value) => writeln!(stdout, "= {}", format_number(value, flags.format))?, 
+        Err(err) => writeln!(stderr, "error: {err}")?, 
+    }
+    Ok(true)
 }
 
-/// Operation precedence. In addition to the regular algebraic operator precedence, the distance between the
-/// operator and the operand is also used. 
-#[derive(Clone, Copy, PartialEq)]
-struct Precedence {
-    spacing: usize, 
-    algebraic: usize, 
-}
-
-/// If the space between an operand and two operators are equal, the operator with the greatest algebraic
-/// precedence is chosen.  
-impl PartialOrd for Precedence {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let spacing = other.spacing.cmp(&self.spacing);
-        let algebraic = other.algebraic.cmp(&self.algebraic);
-        let ordering = match spacing {
-            Ordering::Equal => algebraic, 
-            _ => spacing, 
+/// Reads expressions line by line from stdin until `quit` or EOF
+fn repl(flags: Flags) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(())
+        };
+        let line = line?;
+        match line.trim() {
+            "" => continue, 
+            "quit" => return Ok(()), 
+            input => run(input, flags, &mut io::stdout(), &mut io::stderr())?, 
         };
-        Some(ordering)
     }
 }
 
-/// Entry-point to the parsing algorithm. Parses a string into our AST
-fn parse(string: &str) -> Option<Ast> {
-    let mut tokens = Tokens::from(string);
-    let min_precedence = Precedence {
-        spacing: usize::MAX,
-        algebraic: usize::MAX,
+/// Gathers the expression to evaluate: the argument if one is given, otherwise all of `stdin`. Returns `None`
+/// if neither holds anything but whitespace
+fn read_input(arg: Option<String>, mut stdin: impl Read) -> io::Result<Option<String>> {
+    let input = match arg {
+        Some(arg) => arg, 
+        None => {
+            let mut input = String::new();
+            stdin.read_to_string(&mut input)?;
+            input
+        }
     };
-    let expr = parse_expression(&mut tokens, min_precedence)?;
-    tokens.next()
-        .is_none()
-        .then_some(expr)
+    Ok(Some(input).filter(|input| !input.trim().is_empty()))
 }
 
-/// Parses our AST from a set of lexical tokens. Based on the operator-precedence parser detailed in 
-/// https://en.wikipedia.org/wiki/Operator-precedence_parser
-fn parse_expression(tokens: &mut Tokens, min: Precedence) -> Option<Ast> {
-    parse_primary(tokens).and_then(|lhs| parse_precedence(lhs, tokens, min))
+/// Removes all occurrences of a flag from the arguments, returning whether there were any
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
 }
 
-/// Attempts to parse a binary operation from a left-hand side. If the lhs is not proceeded by a binary
-/// operation, lhs is transparently returned
-fn parse_precedence(mut lhs: Ast, tokens: &mut Tokens, min: Precedence) -> Option<Ast> {
-    // attempts to read a binary operator including its precedence from the tokens
-    let peek_op = |tokens: &mut Tokens| {
-        let &Token::Symbol(op, spacing) = tokens.peek()? else {
-            return None
-        };
-        let algebraic = match op {
-            '+' => 2, 
-            '-' => 2, 
-            '*' => 1, 
-            '/' => 1, 
-            _ => return None, 
-        };
-        let prec = Precedence{ spacing, algebraic };
-        Some((op, prec))
+/// Removes the first occurrence of an option from the arguments along with its value, returning the value if
+/// there was one. Fails if the option is the last argument, such that it has no value
+fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == option) else {
+        return Ok(None)
     };
-
-    // parse all operations above the minimum precedence
-    while let Some((op, prec)) = peek_op(tokens).filter(|(_, prec)| prec >= &min) {
-        let _ = tokens.next();
-
-        // compute the precedence of the current operator to the rhs parsed below. if the rhs is proceeded by
-        // another operator, this is precedence that must be exceeded
-        let rhs_prec = Precedence {
-            spacing: tokens.peek().map(Token::spacing)?, 
-            algebraic: prec.algebraic, 
-        };
-        let mut rhs = parse_primary(tokens)?;
-
-        // parse all operations proceeding the rhs that are above `rhs_prec`; this becomes the new rhs
-        while let Some(_) = peek_op(tokens).filter(|(_, sub_prec)| sub_prec > &rhs_prec) {
-            rhs = parse_precedence(rhs, tokens, rhs_prec)?;
-        }
-        lhs = Ast::Binary(op.into(), Box::new((lhs, rhs)))
+    if index + 1 == args.len() {
+        return Err(format!("missing value for `{option}`"))
     }
-    Some(lhs)
+    args.remove(index);
+    Ok(Some(args.remove(index)))
 }
 
-/// Parses literals and unary operations
-fn parse_primary(tokens: &mut Tokens) -> Option<Ast> {
-    let token = tokens.next()?;
-    let mut parse_unary = |op: &str| {
-        let arg_precedence = Precedence {
-            spacing: tokens.peek().map(Token::spacing)?,
-            algebraic: 0,
-        };
-        let arg = parse_expression(tokens, arg_precedence)?;
-        Some(Ast::Unary(op.into(), Box::new(arg)))
-    };
-    let expr = match token {
-        Token::Number(num, _) => Ast::Literal(num),
-        Token::Symbol('-', _) => parse_unary("-")?, 
-        Token::Word("sqrt", _) => parse_unary("sqrt")?, 
-        _ => return None, 
+/// Removes the output flags from the arguments, failing if one has an invalid value
+fn take_flags(args: &mut Vec<String>) -> Result<Flags, String> {
+    let format = match take_option(args, "--format")?.as_deref() {
+        None | Some("plain") => NumberFormat::Plain, 
+        Some("sci") => NumberFormat::Scientific, 
+        Some("eng") => NumberFormat::Engineering, 
+        Some(format) => return Err(format!("unknown format `{format}`, expected sci, eng or plain")), 
     };
-    Some(expr)
+    Ok(Flags {
+        json: take_flag(args, "--json"), 
+        verbose: take_flag(args, "--verbose"), 
+        eval: take_flag(args, "--eval"), 
+        format, 
+    })
 }
 
 fn main() {
-    let input = env::args().nth(1).unwrap();
-    let expr = parse(&input).unwrap();
-    println!("{expr}");
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let flags = match take_flags(&mut args) {
+        Ok(flags) => flags, 
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(2)
+        }
+    };
+    if take_flag(&mut args, "--repl") {
+        if let Err(err) = repl(flags) {
+            eprintln!("error: {err}");
+            process::exit(1)
+        }
+        return
+    }
+    let input = match read_input(args.into_iter().next(), io::stdin()) {
+        Ok(Some(input)) => input, 
+        Ok(None) => {
+            eprintln!("usage: space_precedence_parser [<flags>] <expression> | --repl");
+            eprintln!("       echo <expression> | space_precedence_parser [<flags>]");
+            eprintln!("flags: --json, --verbose, --eval, --format sci|eng|plain");
+            process::exit(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(1)
+        }
+    };
+    match run(&input, flags, &mut io::stdout(), &mut io::stderr()) {
+        Ok(true) => (), 
+        Ok(false) => process::exit(1), 
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(1)
+        }
+    }
 }
 
-#[test]
-fn test() {
-    fn assert_eq(input: &str, expected: &str) {
-        let expr = parse(&input).unwrap();
-        let output = format!("{expr}");
-        assert_eq!(output, expected);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input() {
+        let read = |arg: Option<&str>, stdin: &str| {
+            read_input(arg.map(String::from), stdin.as_bytes()).unwrap()
+        };
+        assert_eq!(read(Some("1 + 2"), "3 + 4"), Some("1 + 2".into()));
+        assert_eq!(read(None, "1 + 2\n"), Some("1 + 2\n".into()));
+        assert_eq!(read(None, ""), None);
+        assert_eq!(read(None, " \n"), None);
+        assert_eq!(read(Some(""), "1 + 2"), None);
     }
 
-    assert_eq("1.2 + 3.4", "(1.2 + 3.4)");
-    assert_eq("1 * 2+3", "(1 * (2 + 3))");
-    assert_eq("1* 2+ 3", "(1 * (2 + 3))");
+    #[test]
+    fn eval() {
+        let run = |args: &[&str]| {
+            let mut args = args.iter().map(|arg| arg.to_string()).collect();
+            let flags = take_flags(&mut args).unwrap();
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            let ok = super::run(&args[0], flags, &mut stdout, &mut stderr).unwrap();
+            (ok, String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+        };
+        let value = 1.0 * ((3.0 + 4.0) - (5.0 / 6.0));
+        assert_eq!(run(&["--eval", "1*    3+4   -   5/6"]), (true, format!("{value}\n"), "".into()));
+        assert_eq!(run(&["3 + 4", "--eval"]), (true, "7\n".into(), "".into()));
+        assert_eq!(run(&["--eval", "1 / 0"]), (false, "".into(), "error: division by zero\n".into()));
+        assert_eq!(run(&["1 / 0"]), (true, "(1 / 0)\n".into(), "error: division by zero\n".into()));
+        assert_eq!(run(&["3 + 4"]), (true, "(3 + 4)\n= 7\n".into(), "".into()));
+        assert_eq!(run(&["# comment"]), (false, "".into(), "error: empty input\n".into()));
+
+        assert_eq!(run(&["--format", "sci", "1500 * 1000", "--eval"]), (true, "1.5e6\n".into(), "".into()));
+        assert_eq!(run(&["1 / 4000", "--format", "eng"]), (true, "(1 / 4000)\n= 250e-6\n".into(), "".into()));
+        assert_eq!(run(&["--eval", "--format", "plain", "2e6"]), (true, "2000000\n".into(), "".into()));
+    }
 
-    assert_eq("1*    3+4   -   5/6",  "(1 * ((3 + 4) - (5 / 6)))");
-    assert_eq("1*    3+4    -   5/6", "((1 * (3 + 4)) - (5 / 6))");
+    #[test]
+    fn flags() {
+        let take = |args: &[&str]| {
+            let mut args = args.iter().map(|arg| arg.to_string()).collect();
+            take_flags(&mut args).map(|flags| (flags.format, args))
+        };
+        assert_eq!(take(&["1", "--format", "eng"]), Ok((NumberFormat::Engineering, vec!["1".into()])));
+        assert_eq!(take(&["--format", "sci", "1"]), Ok((NumberFormat::Scientific, vec!["1".into()])));
+        assert_eq!(take(&["1"]), Ok((NumberFormat::Plain, vec!["1".into()])));
+        assert_eq!(take(&["1", "--format"]), Err("missing value for `--format`".into()));
+        let unknown = "unknown format `hex`, expected sci, eng or plain";
+        assert_eq!(take(&["--format", "hex", "1"]), Err(unknown.into()));
+    }
 
-    assert_eq("sqrt 1", "(sqrt 1)");
-    assert_eq("sqrt sqrt 1 + 1", "((sqrt (sqrt 1)) + 1)");
-    assert_eq("sqrt sqrt  1 + 1", "(sqrt (sqrt (1 + 1)))");
-    assert_eq("sqrt   sqrt 1 + 1", "(sqrt ((sqrt 1) + 1))");
 }
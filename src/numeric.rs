@@ -0,0 +1,131 @@
+use core::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use crate::EvalError;
+
+/// Number types that `eval_numeric` computes in, which is `f64` by default and `BigDecimal` with the
+/// `bigdecimal` feature. Literals are converted from their lexemes where possible rather than from their
+/// `f64` values, such that e.g. `0.1` is exact in a decimal type
+pub trait Numeric:
+    Clone
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + Neg<Output = Self>
+{
+    /// Parses the lexeme of a decimal literal, e.g. `1.25` or `2e-3`, or returns `None` if it's written in a
+    /// form that the type doesn't parse, such as another base
+    fn from_decimal(lexeme: &str) -> Option<Self>;
+
+    /// Converts a value that has no decimal lexeme, e.g. a constant such as `pi`, or returns `None` if the
+    /// type can't represent it, e.g. an infinity
+    fn from_f64(value: f64) -> Option<Self>;
+
+    /// Converts an integer, which every type can represent
+    fn from_i64(value: i64) -> Self;
+
+    /// Nearest `f64` to the value, which is used to report it in errors
+    fn to_f64(&self) -> f64;
+
+    /// Largest integer that's less than or equal to the value
+    fn floor(&self) -> Self;
+
+    /// Raises the value to a power, failing with `EvalError::DivisionByZero` for a zero base with a negative
+    /// exponent like `eval_int` does, rather than e.g. returning an infinity
+    fn power(&self, exponent: &Self) -> Result<Self, EvalError>;
+
+    /// Square root of the value, failing with `EvalError::NegativeSqrt` for negative values
+    fn square_root(&self) -> Result<Self, EvalError>;
+
+    /// Whether the value is NaN, which evaluation fails on like `eval` does
+    fn is_nan(&self) -> bool {
+        false
+    }
+}
+
+impl Numeric for f64 {
+    fn from_decimal(lexeme: &str) -> Option<f64> {
+        lexeme.parse().ok()
+    }
+
+    fn from_f64(value: f64) -> Option<f64> {
+        Some(value)
+    }
+
+    fn from_i64(value: i64) -> f64 {
+        value as f64
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn floor(&self) -> f64 {
+        libm::floor(*self)
+    }
+
+    fn power(&self, exponent: &f64) -> Result<f64, EvalError> {
+        match *self == 0.0 && *exponent < 0.0 {
+            true => Err(EvalError::DivisionByZero), 
+            false => Ok(libm::pow(*self, *exponent)), 
+        }
+    }
+
+    fn square_root(&self) -> Result<f64, EvalError> {
+        match *self < 0.0 {
+            true => Err(EvalError::NegativeSqrt(*self)), 
+            false => Ok(libm::sqrt(*self)), 
+        }
+    }
+
+    fn is_nan(&self) -> bool {
+        f64::is_nan(*self)
+    }
+}
+
+/// Quotients, powers and roots that aren't exact are rounded to the default precision of `BigDecimal`, which
+/// is 100 digits. Powers with non-integer exponents are computed over `f64`
+#[cfg(feature = "bigdecimal")]
+impl Numeric for bigdecimal::BigDecimal {
+    fn from_decimal(lexeme: &str) -> Option<Self> {
+        lexeme.parse().ok()
+    }
+
+    fn from_f64(value: f64) -> Option<Self> {
+        bigdecimal::FromPrimitive::from_f64(value)
+    }
+
+    fn from_i64(value: i64) -> Self {
+        value.into()
+    }
+
+    fn to_f64(&self) -> f64 {
+        bigdecimal::ToPrimitive::to_f64(self).unwrap_or(f64::NAN)
+    }
+
+    fn floor(&self) -> Self {
+        self.with_scale_round(0, bigdecimal::RoundingMode::Floor)
+    }
+
+    fn power(&self, exponent: &Self) -> Result<Self, EvalError> {
+        use bigdecimal::Zero;
+
+        let integer = exponent.is_integer().then(|| bigdecimal::ToPrimitive::to_i64(exponent)).flatten();
+        match integer {
+            // `powi` takes any power of zero to be zero
+            Some(0) => Ok(Self::from_i64(1)), 
+            Some(n) if n < 0 && self.is_zero() => Err(EvalError::DivisionByZero), 
+            Some(n) => Ok(self.powi(n)), 
+            // a zero base with a negative exponent fails over `f64` too
+            None => {
+                let value = Numeric::to_f64(self).power(&Numeric::to_f64(exponent))?;
+                Self::from_f64(value).ok_or(EvalError::NotANumber)
+            }
+        }
+    }
+
+    fn square_root(&self) -> Result<Self, EvalError> {
+        self.sqrt().ok_or_else(|| EvalError::NegativeSqrt(Numeric::to_f64(self)))
+    }
+}
@@ -0,0 +1,1791 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet}, 
+    format, 
+    string::{String, ToString}, 
+    vec::Vec, 
+};
+use core::{
+    cmp::Ordering, 
+    f64::consts, 
+    fmt::{self, Display, Formatter}, 
+    str::FromStr, 
+};
+use crate::{
+    lexer::*, 
+    Ast, 
+    BinOp, 
+    Number, 
+    NumberKind, 
+    Parenthesized, 
+    UnOp, 
+};
+
+/// Operation precedence. In addition to the regular algebraic operator precedence, the distance between the
+/// operator and the operand is also used. A greater precedence binds tighter
+///
+/// ```
+/// use space_precedence_parser::Precedence;
+///
+/// // `1 * 2+3`: the unspaced `+` binds tighter than the spaced `*`
+/// let mul = Precedence::of("*", 1).unwrap();
+/// let add = Precedence::of("+", 0).unwrap();
+/// assert!(add > mul);
+///
+/// // `1 * 2 + 3`: with equal spacing, the algebraic precedence decides
+/// let add = Precedence::of("+", 1).unwrap();
+/// assert!(mul > add);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Precedence {
+    /// Amount of whitespace between the operator and the operand, where less spacing binds tighter
+    pub spacing: usize, 
+    /// Algebraic precedence of the operator, where a lower value binds tighter, e.g. 1 for `*` and 2 for `+`
+    pub algebraic: usize, 
+}
+
+impl Precedence {
+    /// Precedence that's exceeded by all operators; used to parse a complete expression
+    pub const MIN: Precedence = Precedence {
+        spacing: usize::MAX,
+        algebraic: usize::MAX,
+    };
+
+    /// The lowest precedence that's still greater than this one in the given order, i.e. that binds tighter,
+    /// or `None` if nothing binds tighter
+    fn successor(self, order: PrecedenceOrder) -> Option<Precedence> {
+        let successor = match (order, self.spacing, self.algebraic) {
+            (_, 0, 0) => return None, 
+            (PrecedenceOrder::SpacingFirst, spacing, 0) => Precedence {
+                spacing: spacing - 1, 
+                algebraic: usize::MAX, 
+            }, 
+            (PrecedenceOrder::SpacingFirst, spacing, algebraic) => Precedence {
+                spacing, 
+                algebraic: algebraic - 1, 
+            }, 
+            (PrecedenceOrder::AlgebraicFirst, 0, algebraic) => Precedence {
+                spacing: usize::MAX, 
+                algebraic: algebraic - 1, 
+            }, 
+            (PrecedenceOrder::AlgebraicFirst, spacing, algebraic) => Precedence {
+                spacing: spacing - 1, 
+                algebraic, 
+            }, 
+        };
+        Some(successor)
+    }
+
+    /// Compares how tightly two precedences bind, with the component given by the order compared first and
+    /// the other one breaking ties. `Ordering::Greater` means that this precedence binds tighter
+    pub fn cmp_in(&self, other: &Precedence, order: PrecedenceOrder) -> Ordering {
+        let spacing = other.spacing.cmp(&self.spacing);
+        let algebraic = other.algebraic.cmp(&self.algebraic);
+        match order {
+            PrecedenceOrder::SpacingFirst => spacing.then(algebraic), 
+            PrecedenceOrder::AlgebraicFirst => algebraic.then(spacing), 
+        }
+    }
+
+    /// Precedence of a binary operator with the given spacing under the default algebraic precedence, or
+    /// `None` if it isn't a binary operator
+    pub fn of(op: &str, spacing: usize) -> Option<Precedence> {
+        let algebraic = algebraic_precedence(BinOp::from_symbol(op)?);
+        Some(Precedence { spacing, algebraic })
+    }
+}
+
+/// Formats as e.g. `(spacing 1, algebraic 2)`, with `∞` in place of `usize::MAX`
+impl Display for Precedence {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let bound = |x: usize| match x {
+            usize::MAX => "∞".into(), 
+            x => x.to_string(), 
+        };
+        write!(f, "(spacing {}, algebraic {})", bound(self.spacing), bound(self.algebraic))
+    }
+}
+
+/// Spacing is compared first, such that the operator with the least spacing binds tighter. If the space
+/// between an operand and two operators are equal, the operator with the greatest algebraic precedence is
+/// chosen. See `Precedence::cmp_in` for the opposite order
+impl PartialOrd for Precedence {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp_in(other, PrecedenceOrder::SpacingFirst))
+    }
+}
+
+/// Which component of a `Precedence` decides how tightly an operator binds, with the other one only breaking
+/// ties
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrecedenceOrder {
+    /// Spacing decides, such that `1 * 2+3` is `1 * (2 + 3)`, and the algebraic precedence breaks ties
+    #[default]
+    SpacingFirst, 
+    /// The algebraic precedence decides, such that `1 * 2+3` is `(1 * 2) + 3`, and spacing breaks ties
+    /// between operators of the same algebraic precedence, e.g. `1 - 2-3` is `1 - (2 - 3)`
+    AlgebraicFirst, 
+}
+
+/// Reasons for which a string can't be parsed into an AST. Offending tokens are stored as their lexeme
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A token was found where it can't be used, e.g. a binary operator in place of an operand. Contains the
+    /// token and its byte offset into the input
+    UnexpectedToken(String, usize), 
+    /// The input ended while an operand was still expected
+    UnexpectedEof, 
+    /// The input holds nothing but whitespace and comments
+    EmptyInput, 
+    /// A complete expression was parsed but the input continues after it. Contains the first unconsumed token
+    /// and its byte offset into the input
+    TrailingInput(String, usize), 
+    /// The input couldn't be tokenized
+    Lex(LexError), 
+    /// The expression is nested deeper than the maximum depth in the options
+    DepthLimitExceeded, 
+    /// The input has more tokens than the maximum in the options
+    TooManyTokens, 
+    /// A binary operator is spaced differently on either side while `Asymmetry::Reject` is in effect.
+    /// Contains the operator and its byte offset into the input
+    AsymmetricSpacing(String, usize), 
+    /// A comparison follows another at the same precedence while `Chaining::Reject` is in effect, e.g.
+    /// `1 < 2 < 3`. Contains the second comparison operator and its byte offset into the input
+    ChainedComparison(String, usize), 
+    /// The lhs of an `=` isn't a variable, e.g. `3 = x`. Contains the byte offset of the `=` into the input
+    InvalidAssignment(usize), 
+    /// The absolute value of a literal or constant exceeds the maximum in the options. Contains its lexeme
+    /// and byte offset into the input
+    LiteralOutOfRange(String, usize), 
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken(token, _) => write!(f, "unexpected token `{token}`"), 
+            ParseError::UnexpectedEof => write!(f, "unexpected end of input"), 
+            ParseError::EmptyInput => write!(f, "empty input"), 
+            ParseError::TrailingInput(token, _) => {
+                write!(f, "unexpected trailing input starting at `{token}`")
+            }
+            ParseError::Lex(err) => write!(f, "{err}"), 
+            ParseError::DepthLimitExceeded => write!(f, "expression is nested too deeply"), 
+            ParseError::TooManyTokens => write!(f, "input has too many tokens"), 
+            ParseError::AsymmetricSpacing(op, _) => {
+                write!(f, "operator `{op}` is spaced differently on either side")
+            }
+            ParseError::ChainedComparison(op, _) => write!(f, "comparison `{op}` is chained onto another"), 
+            ParseError::InvalidAssignment(_) => write!(f, "only variables can be assigned to"), 
+            ParseError::LiteralOutOfRange(lexeme, _) => write!(f, "literal `{lexeme}` is out of range"), 
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// Renders a parse error as a multi-line diagnostic: the message, followed by the line of the input it
+/// occurred on with the offending token underlined, if the error has a position
+///
+/// ```
+/// # use space_precedence_parser::{parse, render_error};
+/// let input = "1 + * 2";
+/// let err = parse(input).unwrap_err();
+/// assert_eq!(render_error(input, &err), "error: unexpected token `*`\n  1 + * 2\n      ^");
+/// ```
+pub fn render_error(input: &str, err: &ParseError) -> String {
+    let (offset, width) = match err {
+        ParseError::UnexpectedToken(token, offset) 
+        | ParseError::TrailingInput(token, offset) 
+        | ParseError::AsymmetricSpacing(token, offset) 
+        | ParseError::ChainedComparison(token, offset) 
+        | ParseError::LiteralOutOfRange(token, offset) => (*offset, token.chars().count()), 
+        ParseError::UnexpectedEof => (input.trim_end().len(), 1), 
+        ParseError::InvalidAssignment(offset) => (*offset, 1), 
+        _ => return format!("error: {err}"), 
+    };
+    let start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = input[offset..].find('\n').map_or(input.len(), |i| offset + i);
+    // tabs are kept in the underline so that it lines up with the input regardless of tab width
+    let indent: String = input[start..offset].chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    format!("error: {err}\n  {}\n  {indent}{}", &input[start..end], "^".repeat(width.max(1)))
+}
+
+/// Names of the functions that are parsed as unary operations by default
+const FUNCTIONS: &[&str] = &["sqrt", "sin", "cos", "tan", "ln", "log", "abs", "exp"];
+
+/// Names of the functions that are called with a comma-separated argument list in parentheses directly
+/// following the name, e.g. `log(8, 2)`. Unary functions may be called this way too, where a call with a
+/// single argument is parsed as the unary operation, such that `log(8)` is the same as `log 8`
+const CALLS: &[&str] = &["log", "max", "min"];
+
+/// Words of the `let name = value in body` syntax, which are never read as variables or constants
+const KEYWORDS: &[&str] = &["let", "in"];
+
+/// Names of the constants that are parsed as literals by default. Note that `e` is only read as a constant on
+/// its own, since an `e` directly following a number is part of its exponent, e.g. `1e3`. There's no `nan`,
+/// since evaluation rejects NaN, see `EvalError::NotANumber`
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", consts::PI), 
+    ("e", consts::E), 
+    ("tau", consts::TAU), 
+    ("inf", f64::INFINITY), 
+];
+
+/// Default algebraic precedence of a binary operator, where a lower value binds tighter
+pub(crate) fn algebraic_precedence(op: BinOp) -> usize {
+    match op {
+        BinOp::BitOr => 6, 
+        BinOp::BitAnd => 5, 
+        BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => 4, 
+        BinOp::Shl | BinOp::Shr => 3, 
+        BinOp::Add | BinOp::Sub => 2, 
+        BinOp::Mul | BinOp::Div | BinOp::FloorDiv | BinOp::Rem => 1, 
+        BinOp::Pow => 0, 
+    }
+}
+
+/// Operators that are applied to the operand preceding them, e.g. `3!`. Symbols that are also binary
+/// operators, i.e. `%`, are only postfix where `is_percent` allows
+const POSTFIX: &[UnOp] = &[UnOp::Fact, UnOp::Percent];
+
+/// Algebraic precedence of postfix operators, which bind at least as tight as any binary operator
+const POSTFIX_PRECEDENCE: usize = 0;
+
+/// Algebraic precedence of the conditional `cond ? then : else`, which binds looser than any binary operator
+/// by default. It's right-associative, such that `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+const TERNARY_PRECEDENCE: usize = 7;
+
+/// Compares how tightly two binary operators bind given their spacing from a shared operand, under the
+/// default algebraic precedence. `Ordering::Greater` means that the first operator binds tighter. Returns
+/// `None` if either isn't a binary operator
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use space_precedence_parser::compare_operators;
+///
+/// // a wider space binds looser, regardless of the algebraic precedence
+/// assert_eq!(compare_operators(("*", 2), ("+", 1)), Some(Ordering::Less));
+/// assert_eq!(compare_operators(("*", 1), ("+", 1)), Some(Ordering::Greater));
+/// assert_eq!(compare_operators(("-", 1), ("+", 1)), Some(Ordering::Equal));
+/// assert_eq!(compare_operators(("?", 1), ("+", 1)), None);
+/// ```
+pub fn compare_operators(a: (&str, usize), b: (&str, usize)) -> Option<Ordering> {
+    Precedence::of(a.0, a.1)?.partial_cmp(&Precedence::of(b.0, b.1)?)
+}
+
+/// Whether a unary operator is applied to the operand preceding it rather than the one following it
+pub(crate) fn is_postfix(op: &UnOp) -> bool {
+    POSTFIX.contains(op)
+}
+
+/// Direction in which chained operations of the same binary operator and precedence are grouped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    /// Group from the left, e.g. `2 - 3 - 4 = (2 - 3) - 4`
+    Left, 
+    /// Group from the right, e.g. `2 ^ 3 ^ 2 = 2 ^ (3 ^ 2)`
+    Right, 
+}
+
+/// How a binary operator that's spaced differently on either side, e.g. `1 +2`, binds its operands
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Asymmetry {
+    /// Each side is bound by its own spacing: the lhs by the space before the operator, and the rhs by the
+    /// space after it. E.g. `2 * 3+ 4` is `2 * (3 + 4)`, since the `+` binds the `3` tightly
+    #[default]
+    Split, 
+    /// Both sides are bound by the wider of the two spaces, e.g. `2 * 3+ 4` is `(2 * 3) + 4`
+    Max, 
+    /// Asymmetric spacing is an error, `ParseError::AsymmetricSpacing`
+    Reject, 
+}
+
+/// How comparisons that are chained at the same precedence, e.g. `1 < 2 < 3`, are parsed. Comparisons that
+/// are grouped by parentheses or spacing, e.g. `(1 < 2) < 3` or `1 < 2  <  3`, aren't chained
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chaining {
+    /// Chained comparisons are an error, `ParseError::ChainedComparison`, since they seldom mean what they
+    /// appear to
+    #[default]
+    Reject, 
+    /// Chained comparisons hold if each pair of adjacent operands compares, like in Python. E.g. `a < b < c`
+    /// is `a < b ? b < c : 0`, where the middle operand is duplicated
+    Conjunction, 
+}
+
+/// Binary operators that are right-associative by default; all others are left-associative
+const RIGHT_ASSOCIATIVE: &[BinOp] = &[BinOp::Pow];
+
+/// Whether chained operations of a binary operator group from the right by default
+pub(crate) fn is_right_associative(op: BinOp) -> bool {
+    RIGHT_ASSOCIATIVE.contains(&op)
+}
+
+/// Options that alter how expressions are parsed. The default options are used by `parse`
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// Whether two adjacent operands without an operator in between are multiplied, e.g. `2 3` or `2(3 + 4)`.
+    /// The precedence of the implicit multiplication is determined by the spacing between the operands
+    pub implicit_multiplication: bool, 
+    /// Algebraic precedence of each binary operator, where a lower value binds tighter. Operators missing
+    /// from the table aren't parsed
+    pub precedence: BTreeMap<BinOp, usize>, 
+    /// Associativity of each binary operator. Operators missing from the table are left-associative
+    pub associativity: BTreeMap<BinOp, Associativity>, 
+    /// Names of the functions that are parsed as unary operations, e.g. `sqrt`
+    pub functions: BTreeSet<String>, 
+    /// Names of the constants that are parsed as literals, along with their values, e.g. `pi`
+    pub constants: BTreeMap<String, f64>, 
+    /// Whether words that are neither functions nor constants are parsed as variables. If disabled, such
+    /// words are unexpected tokens
+    pub variables: bool, 
+    /// How binary operators that are spaced differently on either side bind their operands
+    pub asymmetry: Asymmetry, 
+    /// Whether spacing or the algebraic precedence is compared first when deciding how operators group
+    pub precedence_order: PrecedenceOrder, 
+    /// Whether spacing is ignored, such that grouping follows the algebraic precedence alone like in a
+    /// conventional calculator, e.g. `1 *    2 + 3` is `(1 * 2) + 3`. All tokens are then read as unspaced,
+    /// so unary operators only take operands that bind tighter than any binary operator, e.g. `sqrt 4 + 5`
+    /// is `(sqrt 4) + 5`
+    pub normalize_spacing: bool, 
+    /// How comparisons that are chained at the same precedence, e.g. `1 < 2 < 3`, are parsed
+    pub chaining: Chaining, 
+    /// Whether a `%` directly following its operand is a percentage, e.g. `50%` is `0.5`, whereas a spaced
+    /// `7 % 3` remains a modulo. A `%` that's directly followed by an operand is always a modulo, e.g. `7%3`
+    pub percent: bool, 
+    /// Maximum depth of nested sub-expressions, e.g. parentheses and operands of unary operators, which
+    /// guards against overflowing the stack on pathological input
+    pub max_depth: usize, 
+    /// Maximum number of tokens in the input, which bounds the work done on untrusted input before any of it
+    /// is parsed. Unlimited if `None`
+    pub max_tokens: Option<usize>, 
+    /// Maximum absolute value of literals and constants, e.g. to guard conversions to fixed-point numbers
+    /// after parsing. Note that negative literals are negations, e.g. `-5` is bounded like `5`. Unbounded if
+    /// `None`
+    pub max_literal: Option<f64>, 
+    /// Options for tokenizing the input, e.g. how much spacing a tab counts as
+    pub lex: LexOptions, 
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        let precedence = BinOp::ALL.iter()
+            .map(|&op| (op, algebraic_precedence(op)))
+            .collect();
+        let associativity = BinOp::ALL.iter()
+            .map(|&op| match is_right_associative(op) {
+                true => (op, Associativity::Right), 
+                false => (op, Associativity::Left), 
+            })
+            .collect();
+        ParseOptions {
+            implicit_multiplication: false, 
+            precedence, 
+            associativity, 
+            functions: FUNCTIONS.iter().map(|&name| name.into()).collect(), 
+            constants: CONSTANTS.iter().map(|&(name, value)| (name.into(), value)).collect(), 
+            variables: true, 
+            asymmetry: Asymmetry::Split, 
+            precedence_order: PrecedenceOrder::SpacingFirst, 
+            normalize_spacing: false, 
+            chaining: Chaining::Reject, 
+            percent: false, 
+            max_depth: 256, 
+            max_tokens: None, 
+            max_literal: None, 
+            lex: LexOptions::default(), 
+        }
+    }
+}
+
+/// Entry-point to the parsing algorithm. Parses a string into our AST
+pub fn parse(string: &str) -> Result<Ast, ParseError> {
+    parse_with(string, &ParseOptions::default())
+}
+
+/// Parses a string into our AST using the given options
+pub fn parse_with(string: &str, options: &ParseOptions) -> Result<Ast, ParseError> {
+    parse_traced(string, options, &|_| ())
+}
+
+/// Parses the statement at the start of a string using the default options, returning it along with the rest
+/// of the string that follows it, or `None` if there's no valid statement at the start. Useful for reading
+/// multiple expressions from one string, e.g. `1+2 3+4` is parsed as `1 + 2` with ` 3+4` remaining
+///
+/// ```
+/// # use space_precedence_parser::parse_prefix;
+/// let (first, rest) = parse_prefix("1+2 3*4").unwrap();
+/// assert_eq!(first.to_string(), "(1 + 2)");
+/// let (second, rest) = parse_prefix(rest).unwrap();
+/// assert_eq!(second.to_string(), "(3 * 4)");
+/// assert!(rest.is_empty());
+/// ```
+pub fn parse_prefix(string: &str) -> Option<(Ast, &str)> {
+    parse_prefix_with(string, &ParseOptions::default())
+}
+
+/// Parses the statement at the start of a string using the given options like `parse_prefix`
+pub fn parse_prefix_with<'a>(string: &'a str, options: &ParseOptions) -> Option<(Ast, &'a str)> {
+    let mut tokens = tokenize(string, options).ok()?;
+    let statement = parse_statement(&mut tokens, options, &|_| ()).ok()?;
+    Some((statement, &string[tokens.offset()..]))
+}
+
+/// Decisions made while parsing, as reported to the callback of `parse_traced`. `depth` is the nesting of
+/// the sub-expression that the decision is made in
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trace {
+    /// A binary operator, possibly an implicit multiplication, binds at least as tight as the minimum
+    /// precedence of the expression it continues, and is thereby applied to the lhs parsed so far
+    Binary {
+        op: BinOp, 
+        precedence: Precedence, 
+        min: Precedence, 
+        depth: usize, 
+    }, 
+    /// A postfix operator binds at least as tight as the minimum precedence, and is applied to the lhs
+    Postfix {
+        op: UnOp, 
+        precedence: Precedence, 
+        min: Precedence, 
+        depth: usize, 
+    }, 
+    /// A conditional's `?` binds at least as tight as the minimum precedence, such that the lhs parsed so far
+    /// becomes its condition
+    Ternary {
+        precedence: Precedence, 
+        min: Precedence, 
+        depth: usize, 
+    }, 
+    /// The rhs of a binary operator is followed by an operator of at least the given precedence, which is
+    /// parsed by recursing such that it becomes part of the rhs
+    Recurse {
+        min: Precedence, 
+        depth: usize, 
+    }, 
+    /// A prefix operator or function takes all operations of at least the given precedence as its argument
+    Prefix {
+        op: UnOp, 
+        min: Precedence, 
+        depth: usize, 
+    }, 
+}
+
+/// Formats as a single line describing the decision, indented by the depth
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Trace::Binary { op, precedence, min, depth } => {
+                write!(f, "{}binary `{op}` at {precedence} >= {min}", "  ".repeat(*depth))
+            }
+            Trace::Postfix { op, precedence, min, depth } => {
+                write!(f, "{}postfix `{op}` at {precedence} >= {min}", "  ".repeat(*depth))
+            }
+            Trace::Ternary { precedence, min, depth } => {
+                write!(f, "{}conditional at {precedence} >= {min}", "  ".repeat(*depth))
+            }
+            Trace::Recurse { min, depth } => {
+                write!(f, "{}recurse into rhs for >= {min}", "  ".repeat(*depth))
+            }
+            Trace::Prefix { op, min, depth } => {
+                write!(f, "{}prefix `{op}` takes >= {min}", "  ".repeat(*depth))
+            }
+        }
+    }
+}
+
+/// Parses a string into our AST using the given options, calling `trace` with each precedence decision made
+/// along the way. Useful for seeing how the spacing of a tricky input is interpreted
+///
+/// ```
+/// use std::cell::RefCell;
+/// use space_precedence_parser::{parse_traced, ParseOptions};
+///
+/// let lines = RefCell::new(Vec::new());
+/// parse_traced("1 + 2*3", &ParseOptions::default(), &|trace| lines.borrow_mut().push(trace.to_string()))
+///     .unwrap();
+/// assert_eq!(lines.into_inner(), [
+///     "binary `+` at (spacing 1, algebraic 2) >= (spacing ∞, algebraic ∞)", 
+///     "recurse into rhs for >= (spacing 1, algebraic 1)", 
+///     "  binary `*` at (spacing 0, algebraic 1) >= (spacing 1, algebraic 1)", 
+/// ]);
+/// ```
+pub fn parse_traced(
+    string: &str, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Ast, ParseError> {
+    let mut tokens = tokenize(string, options)?;
+    let mut statements = Vec::new();
+    loop {
+        statements.push(parse_statement(&mut tokens, options, trace)?);
+        match tokens.next() {
+            Some(Token::Symbol(";", ..)) => continue, 
+            Some(token) => return Err(ParseError::TrailingInput(token.to_string(), token.span().0)), 
+            None => match tokens.last_error() {
+                Some(err) => return Err(ParseError::Lex(err.clone())), 
+                None => break, 
+            }
+        }
+    }
+    match statements.len() {
+        1 => Ok(statements.remove(0)), 
+        _ => Ok(Ast::Seq(statements)), 
+    }
+}
+
+/// Produces the tokens of the input, failing if there are none or too many of them
+fn tokenize<'a>(string: &'a str, options: &ParseOptions) -> Result<Tokens<'a>, ParseError> {
+    let mut lex = options.lex.clone();
+    if options.normalize_spacing {
+        lex.max_spacing = Some(0);
+    }
+    let mut tokens = Tokens::with_options(string, lex);
+    if tokens.peek().is_none() && tokens.last_error().is_none() {
+        return Err(ParseError::EmptyInput)
+    }
+    // the tokens are counted on a copy that's discarded as it goes, such that no more than the limit of them
+    // is ever tokenized, and none of them are held in memory
+    if let Some(max) = options.max_tokens {
+        if tokens.snapshot().nth(max).is_some() {
+            return Err(ParseError::TooManyTokens)
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses a statement, which is either an expression or an assignment `name = value` of one to a variable
+fn parse_statement(
+    tokens: &mut Tokens, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Ast, ParseError> {
+    let expr = parse_expression(tokens, Precedence::MIN, 0, options, trace)?;
+    let Some(&Token::Symbol("=", _, (offset, _))) = tokens.peek() else {
+        return Ok(expr)
+    };
+    let Ast::Variable(name) = expr else {
+        return Err(ParseError::InvalidAssignment(offset))
+    };
+    let _ = tokens.next();
+    let value = parse_expression(tokens, Precedence::MIN, 0, options, trace)?;
+    Ok(Ast::assign(name, value))
+}
+
+/// Parses a string into our AST using the default options, e.g. `let expr: Ast = "1 + 2".parse()?`
+impl FromStr for Ast {
+    type Err = ParseError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        parse(string)
+    }
+}
+
+/// Produces the error for when tokens unexpectedly run out, which is either due to the end of input or due to
+/// a lexing error
+fn eof(tokens: &Tokens) -> ParseError {
+    tokens.last_error()
+        .cloned()
+        .map_or(ParseError::UnexpectedEof, ParseError::Lex)
+}
+
+/// The error for a token that can't be used where it was found
+fn unexpected(token: &Token) -> ParseError {
+    ParseError::UnexpectedToken(token.to_string(), token.span().0)
+}
+
+/// Increments the depth of nesting, failing if it exceeds the maximum depth in the options
+fn descend(depth: usize, options: &ParseOptions) -> Result<usize, ParseError> {
+    match depth < options.max_depth {
+        true => Ok(depth + 1), 
+        false => Err(ParseError::DepthLimitExceeded), 
+    }
+}
+
+/// Parses our AST from a set of lexical tokens. Based on the operator-precedence parser detailed in 
+/// https://en.wikipedia.org/wiki/Operator-precedence_parser
+fn parse_expression(
+    tokens: &mut Tokens, 
+    min: Precedence, 
+    depth: usize, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Ast, ParseError> {
+    let lhs = parse_primary(tokens, min, depth, options, trace)?;
+    parse_precedence(lhs, tokens, min, depth, options, trace)
+}
+
+/// Whether the next token, a `%`, is a percentage rather than a modulo. That's the case if percentages are
+/// enabled and it directly follows its operand without being directly followed by another, e.g. `50%` and
+/// `50% + 1`, but not `7 % 3` or `7%3`
+fn is_percent(tokens: &mut Tokens, options: &ParseOptions) -> bool {
+    let operand_follows = matches!(
+        tokens.peek_nth(1), 
+        Some(Token::Symbol("(", 0, _) | Token::Number(_, _, 0, _) | Token::Word(_, 0, _)), 
+    );
+    options.percent && tokens.peek().is_some_and(|token| token.spacing() == 0) && !operand_follows
+}
+
+/// Reads the spacing of the next token, which is expected to be an operand
+fn peek_spacing(tokens: &mut Tokens) -> Result<usize, ParseError> {
+    match tokens.peek() {
+        Some(token) => Ok(token.spacing()), 
+        None => Err(eof(tokens)), 
+    }
+}
+
+/// The ways in which an operator read by `parse_precedence` is applied
+#[derive(Clone, Copy, PartialEq)]
+enum Fixity {
+    /// Binary operator between the lhs and rhs
+    Infix(BinOp), 
+    /// Multiplication that's implied by two adjacent operands, i.e. without a token of its own
+    Implicit, 
+    /// Unary operator following its operand
+    Postfix(&'static UnOp), 
+    /// The `?` of a conditional, which is followed by the `then` branch, a `:` and the `else` branch
+    Ternary, 
+}
+
+/// Attempts to parse a binary or postfix operation from a left-hand side. If the lhs is not proceeded by an
+/// operation, lhs is transparently returned
+fn parse_precedence<'a>(
+    mut lhs: Ast, 
+    tokens: &mut Tokens<'a>, 
+    min: Precedence, 
+    depth: usize, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Ast, ParseError> {
+    // attempts to read an operator including its precedence from the tokens. an operand directly following
+    // the lhs is read as an implicit multiplication, if enabled, in which case there's no operator token to
+    // consume
+    let order = options.precedence_order;
+    let peek_op = |tokens: &mut Tokens<'a>| {
+        let implicit = options.implicit_multiplication;
+        let token = *tokens.peek()?;
+        let (spacing, fixity) = match token {
+            Token::Symbol("(" | "√", spacing, _) if implicit => (spacing, Fixity::Implicit), 
+            Token::Symbol("?", spacing, _) => (spacing, Fixity::Ternary), 
+            Token::Symbol("%", spacing, _) if is_percent(tokens, options) => {
+                (spacing, Fixity::Postfix(&UnOp::Percent))
+            }
+            Token::Symbol(symbol, spacing, _) => match BinOp::from_symbol(symbol) {
+                Some(op) => (spacing, Fixity::Infix(op)), 
+                None => (spacing, Fixity::Postfix(POSTFIX.iter().find(|op| op.name() == symbol)?)), 
+            }
+            // the `in` of a binding ends its value rather than multiplying it
+            Token::Word(word, ..) if KEYWORDS.contains(&word) => return None, 
+            Token::Number(_, _, spacing, _) | Token::Word(_, spacing, _) if implicit => {
+                (spacing, Fixity::Implicit)
+            }
+            _ => return None, 
+        };
+        // the lhs is bound by the spacing preceding a binary operator, unless both sides use the wider one
+        let spacing = match (fixity, options.asymmetry) {
+            (Fixity::Infix(_), Asymmetry::Max) => {
+                spacing.max(tokens.peek_nth(1).map_or(0, Token::spacing))
+            }
+            _ => spacing, 
+        };
+        let algebraic = match fixity {
+            Fixity::Postfix(_) => POSTFIX_PRECEDENCE, 
+            Fixity::Ternary => TERNARY_PRECEDENCE, 
+            Fixity::Infix(op) => *options.precedence.get(&op)?, 
+            Fixity::Implicit => *options.precedence.get(&BinOp::Mul)?, 
+        };
+        let prec = Precedence{ spacing, algebraic };
+        Some((fixity, prec))
+    };
+
+    // parses the rhs of an operator of the given precedence, which includes all operations proceeding it
+    // that bind tighter than the operator. for right-associative operators, operations of equal precedence
+    // are also grouped into the rhs, whereas for left-associative ones they're left to the caller such that
+    // they group from the left
+    let parse_rhs = |tokens: &mut Tokens<'a>, prec: Precedence, right_assoc: bool| {
+        // the precedence of the operator to the rhs is determined by the spacing following it, rather than
+        // preceding it, unless both sides use the wider one. if the rhs is proceeded by another operator,
+        // this is precedence that must be exceeded
+        let spacing = match options.asymmetry {
+            Asymmetry::Max => peek_spacing(tokens)?.max(prec.spacing), 
+            _ => peek_spacing(tokens)?, 
+        };
+        let rhs_prec = Precedence {
+            spacing, 
+            algebraic: prec.algebraic, 
+        };
+        let mut rhs = parse_primary(tokens, rhs_prec, depth, options, trace)?;
+        let sub_min = if right_assoc { Some(rhs_prec) } else { rhs_prec.successor(order) };
+        let Some(sub_min) = sub_min else {
+            return Ok(rhs)
+        };
+        while peek_op(tokens).filter(|(_, sub_prec)| sub_prec.cmp_in(&sub_min, order).is_ge()).is_some() {
+            trace(&Trace::Recurse { min: sub_min, depth });
+            rhs = parse_precedence(rhs, tokens, sub_min, descend(depth, options)?, options, trace)?;
+        }
+        Ok(rhs)
+    };
+
+    // parse all operations above the minimum precedence. the precedence and rhs of the last operation are
+    // kept if it was a comparison, such that a comparison following it at the same precedence is recognized
+    // as a chain
+    let mut comparison: Option<(Precedence, Ast)> = None;
+    while let Some((fixity, prec)) = peek_op(tokens).filter(|(_, prec)| prec.cmp_in(&min, order).is_ge()) {
+        let is_comparison = matches!(fixity, Fixity::Infix(op) if op.is_comparison());
+        let chained = comparison.take().filter(|(last, _)| is_comparison && *last == prec);
+        let op = match fixity {
+            Fixity::Infix(op) => {
+                let offset = tokens.next().map_or(0, |token| token.span().0);
+                if options.asymmetry == Asymmetry::Reject && peek_spacing(tokens)? != prec.spacing {
+                    return Err(ParseError::AsymmetricSpacing(op.symbol().into(), offset))
+                }
+                if chained.is_some() && options.chaining == Chaining::Reject {
+                    return Err(ParseError::ChainedComparison(op.symbol().into(), offset))
+                }
+                op
+            }
+            Fixity::Implicit => BinOp::Mul, 
+            Fixity::Postfix(op) => {
+                let _ = tokens.next();
+                trace(&Trace::Postfix { op: op.clone(), precedence: prec, min, depth });
+                lhs = Ast::unary(op.clone(), lhs);
+                continue
+            }
+            // the `then` branch is delimited by the `?` and `:` like a parenthesized expression, so its
+            // grouping is independent of the spacing around it
+            Fixity::Ternary => {
+                let _ = tokens.next();
+                trace(&Trace::Ternary { precedence: prec, min, depth });
+                let then_depth = descend(depth, options)?;
+                let then = parse_expression(tokens, Precedence::MIN, then_depth, options, trace)?;
+                match tokens.next() {
+                    Some(Token::Symbol(":", ..)) => (), 
+                    Some(token) => return Err(unexpected(&token)), 
+                    None => return Err(eof(tokens)), 
+                }
+                let otherwise = parse_rhs(tokens, prec, true)?;
+                lhs = Ast::ternary(lhs, then, otherwise);
+                continue
+            }
+        };
+        trace(&Trace::Binary { op, precedence: prec, min, depth });
+        let right_assoc = options.associativity.get(&op) == Some(&Associativity::Right);
+        let rhs = parse_rhs(tokens, prec, right_assoc)?;
+        if op.is_comparison() {
+            comparison = Some((prec, rhs.clone()));
+        }
+        lhs = match chained {
+            Some((_, middle)) => Ast::ternary(lhs, Ast::binary(op, middle, rhs), Ast::literal(0.0)), 
+            None => Ast::binary(op, lhs, rhs), 
+        }
+    }
+    Ok(lhs)
+}
+
+/// Parses literals, variables, unary operations, and parenthesized sub-expressions. `enclosing` is the
+/// precedence of the expression that the primary is an operand of, e.g. the rhs of a binary operator
+fn parse_primary(
+    tokens: &mut Tokens, 
+    enclosing: Precedence, 
+    depth: usize, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Ast, ParseError> {
+    let token = tokens.next().ok_or_else(|| eof(tokens))?;
+    let is_function = |word| CALLS.contains(&word) || options.functions.contains(word);
+    let is_call = matches!(token, Token::Word(word, ..) if is_function(word))
+        && matches!(tokens.peek(), Some(Token::Symbol("(", 0, _)));
+
+    // the argument of a unary operator is bound by the spacing between the operator and the argument, but
+    // never by more than the spacing of the enclosing expression. this keeps the argument within the operand
+    // it's part of, e.g. `1 * -  2 + 3` is read as `(1 * -2) + 3` rather than `1 * -(2 + 3)`. nested unary
+    // operators share the same enclosing expression
+    let mut parse_unary = |op: UnOp| {
+        let arg_precedence = Precedence {
+            spacing: peek_spacing(tokens)?.min(enclosing.spacing),
+            algebraic: 0,
+        };
+        trace(&Trace::Prefix { op: op.clone(), min: arg_precedence, depth });
+        let depth = descend(depth, options)?;
+        let arg = parse_primary(tokens, enclosing, depth, options, trace)?;
+        let arg = parse_precedence(arg, tokens, arg_precedence, depth, options, trace)?;
+        Ok(Ast::unary(op, arg))
+    };
+    let in_range = |value: f64| match options.max_literal {
+        Some(max) if value.abs() > max => {
+            Err(ParseError::LiteralOutOfRange(token.to_string(), token.span().0))
+        }
+        _ => Ok(value), 
+    };
+    let expr = match token {
+        Token::Number(value, lexeme, ..) => Ast::Literal(Number {
+            value: in_range(value)?, 
+            kind: match is_integer(lexeme, &options.lex) {
+                true => NumberKind::Int, 
+                false => NumberKind::Float, 
+            }, 
+            lexeme: Some(lexeme.into()), 
+        }), 
+        Token::Symbol("(", ..) => {
+            let depth = descend(depth, options)?;
+            let mut expr = parse_expression(tokens, Precedence::MIN, depth, options, trace)?;
+            if let Ast::Binary { parenthesized, .. } = &mut expr {
+                *parenthesized = Parenthesized(true);
+            }
+            match tokens.next() {
+                Some(Token::Symbol(")", ..)) => expr, 
+                Some(token) => return Err(unexpected(&token)), 
+                None => return Err(eof(tokens)), 
+            }
+        }
+        Token::Symbol("-", ..) => parse_unary(UnOp::Neg)?, 
+        Token::Symbol("+", ..) => parse_unary(UnOp::Plus)?, 
+        Token::Symbol("~", ..) => parse_unary(UnOp::Not)?, 
+        // shorthand for `sqrt`, which is a symbol rather than a word, such that it needs no space, e.g. `√4`
+        Token::Symbol("√", ..) => parse_unary(UnOp::Sqrt)?, 
+        Token::Word(word, ..) if is_call => {
+            let _ = tokens.next();
+            let mut args = parse_arguments(tokens, descend(depth, options)?, options, trace)?;
+            match args.len() {
+                1 if options.functions.contains(word) => Ast::unary(UnOp::from_name(word), args.remove(0)), 
+                _ => Ast::Call(word.into(), args), 
+            }
+        }
+        Token::Word(word, ..) if options.functions.contains(word) => parse_unary(UnOp::from_name(word))?, 
+        // like the `then` branch of a conditional, the value is delimited by keywords, whereas the body
+        // extends as far as possible
+        Token::Word("let", ..) => {
+            let depth = descend(depth, options)?;
+            let name = match tokens.next() {
+                Some(Token::Word(name, ..)) if !KEYWORDS.contains(&name) => name, 
+                Some(token) => return Err(unexpected(&token)), 
+                None => return Err(eof(tokens)), 
+            };
+            match tokens.next() {
+                Some(Token::Symbol("=", ..)) => (), 
+                Some(token) => return Err(unexpected(&token)), 
+                None => return Err(eof(tokens)), 
+            }
+            let value = parse_expression(tokens, Precedence::MIN, depth, options, trace)?;
+            match tokens.next() {
+                Some(Token::Word("in", ..)) => (), 
+                Some(token) => return Err(unexpected(&token)), 
+                None => return Err(eof(tokens)), 
+            }
+            let body = parse_expression(tokens, Precedence::MIN, depth, options, trace)?;
+            Ast::binding(name, value, body)
+        }
+        Token::Word(word, ..) if KEYWORDS.contains(&word) => return Err(unexpected(&token)), 
+        Token::Word(word, ..) => match options.constants.get(word) {
+            Some(&value) => Ast::literal(in_range(value)?), 
+            None if options.variables => Ast::Variable(word.into()), 
+            None => return Err(unexpected(&token)), 
+        }
+        _ => return Err(unexpected(&token)), 
+    };
+    Ok(expr)
+}
+
+/// Parses the comma-separated arguments of a function call up to and including the closing parenthesis
+fn parse_arguments(
+    tokens: &mut Tokens, 
+    depth: usize, 
+    options: &ParseOptions, 
+    trace: &dyn Fn(&Trace), 
+) -> Result<Vec<Ast>, ParseError> {
+    let mut args = Vec::new();
+    if let Some(Token::Symbol(")", ..)) = tokens.peek() {
+        let _ = tokens.next();
+        return Ok(args)
+    }
+    loop {
+        args.push(parse_expression(tokens, Precedence::MIN, depth, options, trace)?);
+        match tokens.next() {
+            Some(Token::Symbol(",", ..)) => continue, 
+            Some(Token::Symbol(")", ..)) => return Ok(args), 
+            Some(token) => return Err(unexpected(&token)), 
+            None => return Err(eof(tokens)), 
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use proptest::prelude::*;
+    use super::*;
+
+    fn assert_parse(input: &str, expected: &str) {
+        let expr = parse(input).unwrap();
+        let output = format!("{expr}");
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test() {
+        assert_parse("1.2 + 3.4", "(1.2 + 3.4)");
+        assert_parse("1e3 + 2.5e-3", "(1000.0 + 0.0025)");
+        assert_parse(".5 + 1.", "(0.5 + 1.0)");
+        assert_parse("1 * 2+3", "(1 * (2 + 3))");
+        assert_parse("1* 2+ 3", "(1 * (2 + 3))");
+
+        assert_parse("1*    3+4   -   5/6",  "(1 * ((3 + 4) - (5 / 6)))");
+        assert_parse("1*    3+4    -   5/6", "((1 * (3 + 4)) - (5 / 6))");
+
+        assert_parse("sqrt 1", "(sqrt 1)");
+        assert_parse("sqrt sqrt 1 + 1", "((sqrt (sqrt 1)) + 1)");
+        assert_parse("sqrt sqrt  1 + 1", "(sqrt (sqrt (1 + 1)))");
+        assert_parse("sqrt   sqrt 1 + 1", "(sqrt ((sqrt 1) + 1))");
+    }
+
+    #[test]
+    fn power() {
+        assert_parse("2^3^2", "(2 ^ (3 ^ 2))");
+        assert_parse("2 ^ 3 ^ 2", "(2 ^ (3 ^ 2))");
+        assert_parse("2 ^ 3 + 1", "((2 ^ 3) + 1)");
+        assert_parse("2 * 3 ^ 2", "(2 * (3 ^ 2))");
+        assert_parse("2^ 3+1", "(2 ^ (3 + 1))");
+        assert_parse("2^3 ^ 2", "((2 ^ 3) ^ 2)");
+        assert_parse("2 ^ 3^2", "(2 ^ (3 ^ 2))");
+    }
+
+    #[test]
+    fn modulo() {
+        assert_parse("10 % 3", "(10 % 3)");
+        assert_parse("10 % 3 + 1", "((10 % 3) + 1)");
+        assert_parse("10% 3+1", "(10 % (3 + 1))");
+        assert_parse("7 % 3 * 2", "((7 % 3) * 2)");
+        assert_parse("7 * 3 % 2", "((7 * 3) % 2)");
+    }
+
+    #[test]
+    fn functions() {
+        for function in FUNCTIONS {
+            assert_parse(&format!("{function} 1"), &format!("({function} 1)"));
+            assert_parse(&format!("{function}  1 + 2"), &format!("({function} (1 + 2))"));
+        }
+        assert_parse("sin cos 0", "(sin (cos 0))");
+        assert_parse("sin cos 1 + 1", "((sin (cos 1)) + 1)");
+        assert_parse("sin cos  1 + 1", "(sin (cos (1 + 1)))");
+        assert_parse("sin  cos 1 + 1", "(sin ((cos 1) + 1))");
+        assert_parse("ln   exp 1 + 1", "(ln ((exp 1) + 1))");
+    }
+
+    #[test]
+    fn custom_words() {
+        let mut options = ParseOptions {
+            functions: BTreeSet::from(["neg".into()]), 
+            constants: BTreeMap::from([("g".into(), 9.81)]), 
+            ..ParseOptions::default()
+        };
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("neg 5", "(neg 5)");
+        assert_parse("neg  g * 2", "(neg (9.81 * 2))");
+        assert_parse("sqrt", "sqrt");
+        assert_parse("pi * x", "(pi * x)");
+        assert_eq!(parse_with("sqrt 5", &options).unwrap_err(), ParseError::TrailingInput("5".into(), 5));
+
+        options.variables = false;
+        assert!(parse_with("neg g", &options).is_ok());
+        assert_eq!(parse_with("neg x", &options).unwrap_err(), ParseError::UnexpectedToken("x".into(), 4));
+        let err = parse_with("sqrt 5", &options).unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedToken("sqrt".into(), 0));
+    }
+
+    #[test]
+    fn calls() {
+        assert_parse("log(8, 2)", "log(8, 2)");
+        assert_parse("max(1, 2)", "max(1, 2)");
+        assert_parse("min(1, 2 * 3, x)", "min(1, (2 * 3), x)");
+        assert_parse("max()", "max()");
+        assert_parse("max(1)", "max(1)");
+        assert_parse("log(8)", "(log 8)");
+        assert_parse("log (8)", "(log 8)");
+        assert_parse("1 + max(2, 3) * 4", "(1 + (max(2, 3) * 4))");
+        assert_parse("max(1, 2)+3 * 4", "((max(1, 2) + 3) * 4)");
+        assert_parse("max(1 * 2+3, 4)", "max((1 * (2 + 3)), 4)");
+        assert_parse("max(max(1, 2), -3)", "max(max(1, 2), (- 3))");
+        assert_parse("max", "max");
+        assert_parse("max + 1", "(max + 1)");
+        assert_eq!(parse("max(1, 2").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("max(1 2)").unwrap_err(), ParseError::UnexpectedToken("2".into(), 6));
+        assert_eq!(parse("max(1,)").unwrap_err(), ParseError::UnexpectedToken(")".into(), 6));
+        assert_eq!(parse("log (8, 2)").unwrap_err(), ParseError::UnexpectedToken(",".into(), 6));
+        assert_eq!(parse("1, 2").unwrap_err(), ParseError::TrailingInput(",".into(), 1));
+    }
+
+    #[test]
+    fn parenthesized_functions() {
+        assert_eq!(parse("sqrt(4)").unwrap(), parse("sqrt 4").unwrap());
+        assert_parse("sqrt(4) + 1", "((sqrt 4) + 1)");
+        assert_parse("sqrt 4 + 1", "((sqrt 4) + 1)");
+        // the parentheses delimit the argument, so the spacing around the call doesn't matter
+        assert_parse("sqrt(4)+1", "((sqrt 4) + 1)");
+        assert_parse("sqrt(4)^2", "((sqrt 4) ^ 2)");
+        assert_parse("sqrt  (4)^2", "(sqrt (4 ^ 2))");
+        assert_parse("sqrt(4)!", "((sqrt 4) !)");
+        assert_parse("sqrt(2  +  2) * 3", "((sqrt (2 + 2)) * 3)");
+        assert_parse("2 * sin(0)+1", "(2 * ((sin 0) + 1))");
+        assert_parse("sin(cos(0))", "(sin (cos 0))");
+        assert_parse("sqrt(1, 2)", "sqrt(1, 2)");
+    }
+
+    #[test]
+    fn constants() {
+        assert_parse("pi", &consts::PI.to_string());
+        assert_parse("pi * 2", &format!("({} * 2)", consts::PI));
+        assert_parse("sqrt pi", &format!("(sqrt {})", consts::PI));
+        assert_parse("e + tau", &format!("({} + {})", consts::E, consts::TAU));
+        assert_parse("1e3 * e", &format!("(1000.0 * {})", consts::E));
+        assert_eq!(parse("pi 2").unwrap_err(), ParseError::TrailingInput("2".into(), 3));
+    }
+
+    #[test]
+    fn variables() {
+        assert_parse("x", "x");
+        assert_parse("x + y * 2", "(x + (y * 2))");
+        assert_parse("sqrt x_y", "(sqrt x_y)");
+        assert_parse("sqrt  x + pi", &format!("(sqrt (x + {}))", consts::PI));
+        assert_parse("é + ü", "(é + ü)");
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_parse("(1)", "1");
+        assert_parse("(1 + 2) * 3", "((1 + 2) * 3)");
+        assert_parse("2 * (3 + 4)", "(2 * (3 + 4))");
+        assert_parse("(1 + 2)*3", "((1 + 2) * 3)");
+        assert_parse("((1))+ 2", "(1 + 2)");
+
+        // spacing only affects the precedence within the parentheses
+        assert_parse("2 * (1 * 2+3)", "(2 * (1 * (2 + 3)))");
+        assert_parse("(1 * 2+3) * 4", "((1 * (2 + 3)) * 4)");
+        assert_parse("sqrt (1 + 3)", "(sqrt (1 + 3))");
+    }
+
+    #[test]
+    fn signs() {
+        // a `-` following an operand is always a subtraction, where spacing decides which side the operands
+        // are bound to. a `-` in place of an operand is a sign, whose argument is bound by its spacing
+        assert_parse("1-2", "(1 - 2)");
+        assert_parse("1 - 2", "(1 - 2)");
+        assert_parse("1 -2", "(1 - 2)");
+        assert_parse("1 -2 * 3", "((1 - 2) * 3)");
+        assert_parse("1 - 2 * 3", "(1 - (2 * 3))");
+        assert_parse("1 - -2", "(1 - (- 2))");
+        assert_parse("1 --2", "(1 - (- 2))");
+        assert_parse("3 * -2", "(3 * (- 2))");
+        assert_parse("3 * -2 + 1", "((3 * (- 2)) + 1)");
+        assert_parse("3 * -2+1", "(3 * ((- 2) + 1))");
+        assert_parse("3 * - 2", "(3 * (- 2))");
+        assert_parse("-2 * 3", "((- 2) * 3)");
+        assert_parse("-  2 * 3", "(- (2 * 3))");
+    }
+
+    #[test]
+    fn signs_after_operators() {
+        // the spacing between a sign and its argument binds the argument as usual, but the argument never
+        // extends past the operand of the preceding operator
+        assert_parse("1 + -2", "(1 + (- 2))");
+        assert_parse("1 +  - 2", "(1 + (- 2))");
+        assert_parse("1 +-2", "(1 + (- 2))");
+        assert_parse("1 + - 2", "(1 + (- 2))");
+        assert_parse("1 * -2 + 3", "((1 * (- 2)) + 3)");
+        assert_parse("1 * - 2 + 3", "((1 * (- 2)) + 3)");
+        assert_parse("1 * -  2 + 3", "((1 * (- 2)) + 3)");
+        assert_parse("1 * -2+3", "(1 * ((- 2) + 3))");
+        assert_parse("1 * - 2+3", "(1 * (- (2 + 3)))");
+        assert_parse("1 *  - 2+3 + 4", "(1 * ((- (2 + 3)) + 4))");
+        assert_parse("1 *  -  2 + 3", "(1 * (- (2 + 3)))");
+        assert_parse("1 *  -   2 + 3  + 4", "((1 * (- (2 + 3))) + 4)");
+        assert_parse("1 * - -  2 + 3", "((1 * (- (- 2))) + 3)");
+        assert_parse("sin cos  1 + 1", "(sin (cos (1 + 1)))");
+        assert_parse("2 * sqrt 1+3", "(2 * (sqrt (1 + 3)))");
+        assert_parse("2 * sqrt  1 + 3", "((2 * (sqrt 1)) + 3)");
+        assert_parse("-  2 + 3", "(- (2 + 3))");
+        assert_parse("(-  2 + 3)", "(- (2 + 3))");
+    }
+
+    #[test]
+    fn unary_plus() {
+        assert_parse("+5", "(+ 5)");
+        assert_parse("-+5", "(- (+ 5))");
+        assert_parse("-  +3", "(- (+ 3))");
+        assert_parse("+5 * 2", "((+ 5) * 2)");
+        assert_parse("+ 5 * 2", "((+ 5) * 2)");
+        assert_parse("+  5 * 2", "(+ (5 * 2))");
+        assert_parse("-  5 * 2", "(- (5 * 2))");
+        assert_parse("1 + +2", "(1 + (+ 2))");
+    }
+
+    #[test]
+    fn factorial() {
+        assert_parse("5!", "(5 !)");
+        assert_parse("5!!", "((5 !) !)");
+        assert_parse("3! + 2", "((3 !) + 2)");
+        assert_parse("3 ! + 2", "((3 !) + 2)");
+        assert_parse("2 + 3!", "(2 + (3 !))");
+        assert_parse("2 + 3 !", "(2 + (3 !))");
+        assert_parse("2+3 !", "((2 + 3) !)");
+        assert_parse("2 ^ 3!", "(2 ^ (3 !))");
+        assert_parse("2^3 !", "((2 ^ 3) !)");
+        assert_parse("-3!", "(- (3 !))");
+        assert_parse("(1 + 2)!", "((1 + 2) !)");
+        assert_eq!(parse("!3").unwrap_err(), ParseError::UnexpectedToken("!".into(), 0));
+    }
+
+    #[test]
+    fn comparison() {
+        assert_parse("1 < 2", "(1 < 2)");
+        assert_parse("3 > 2 + 2", "(3 > (2 + 2))");
+        assert_parse("1 + 2 > 2 + 0", "((1 + 2) > (2 + 0))");
+        assert_parse("2 * 3 < 7", "((2 * 3) < 7)");
+        assert_parse("1 + 2>2", "(1 + (2 > 2))");
+        assert_parse("1+2 > 2", "((1 + 2) > 2)");
+        assert_parse("1 <= 2", "(1 <= 2)");
+        assert_parse("1 + 1>=2", "(1 + (1 >= 2))");
+        assert_parse("1 + 2 >= 3 * 1", "((1 + 2) >= (3 * 1))");
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_parse("1 + 2 << 3", "((1 + 2) << 3)");
+        assert_parse("1 << 2 < 3", "((1 << 2) < 3)");
+        assert_parse("1 < 2 & 3 < 4", "((1 < 2) & (3 < 4))");
+        assert_parse("1 | 2 & 3", "(1 | (2 & 3))");
+        assert_parse("1 | 2&3 | 4", "((1 | (2 & 3)) | 4)");
+        assert_parse("1 & 2|3", "(1 & (2 | 3))");
+        assert_parse("~1 & 2", "((~ 1) & 2)");
+        assert_parse("~  1 & 2", "(~ (1 & 2))");
+        assert_parse("1<<2>>3", "((1 << 2) >> 3)");
+    }
+
+    #[test]
+    fn floor_division() {
+        let expr = parse("7 // 2").unwrap();
+        assert_eq!(expr, Ast::binary(BinOp::FloorDiv, Ast::literal(7.0), Ast::literal(2.0)));
+        assert_parse("7 // 2 + 1", "((7 // 2) + 1)");
+        assert_parse("7 // 2+1", "(7 // (2 + 1))");
+        assert_parse("8 / 4 // 2", "((8 / 4) // 2)");
+        assert_parse("2 * 7//2", "(2 * (7 // 2))");
+        assert_eq!(parse("7 // 2 * 3").unwrap().to_infix(), "7 // 2 * 3");
+
+        // a spaced `/ /` is two divisions, and `//` starts no comment
+        assert_eq!(parse("7 / / 2").unwrap_err(), ParseError::UnexpectedToken("/".into(), 4));
+        assert_eq!(parse("7 // 2 # comment"), parse("7 // 2"));
+    }
+
+    #[test]
+    fn ternary() {
+        assert_parse("1 > 0 ? 2 : 3", "((1 > 0) ? 2 : 3)");
+        assert_parse("a ? b : c ? d : f", "(a ? b : (c ? d : f))");
+        assert_parse("a ? b ? c : d : f", "(a ? (b ? c : d) : f)");
+        assert_parse("1 + a ? b : c", "((1 + a) ? b : c)");
+        assert_parse("a ? b : c + 1", "(a ? b : (c + 1))");
+        // the branches are still subject to spacing, except for the `then` branch, which is delimited
+        assert_parse("a ? b : c  +  1", "((a ? b : c) + 1)");
+        assert_parse("x  >  0?1:2", "(x > (0 ? 1 : 2))");
+        assert_parse("a ? 1  +  2*3 : 4", "(a ? (1 + (2 * 3)) : 4)");
+        assert_parse("a ? b : c  ?  d : f", "((a ? b : c) ? d : f)");
+
+        assert_eq!(parse("a ? b").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("a ? b, c").unwrap_err(), ParseError::UnexpectedToken(",".into(), 5));
+        assert_eq!(parse("a : b").unwrap_err(), ParseError::TrailingInput(":".into(), 2));
+    }
+
+    #[test]
+    fn asymmetry() {
+        let parse_asymmetric = |input: &str, asymmetry: Asymmetry| {
+            let options = ParseOptions { asymmetry, ..ParseOptions::default() };
+            parse_with(input, &options).map(|expr| expr.to_string())
+        };
+        for asymmetry in [Asymmetry::Split, Asymmetry::Max] {
+            assert_eq!(parse_asymmetric("1 +2", asymmetry).unwrap(), "(1 + 2)");
+            assert_eq!(parse_asymmetric("1+ 2", asymmetry).unwrap(), "(1 + 2)");
+            assert_eq!(parse_asymmetric("1 + 2", asymmetry).unwrap(), "(1 + 2)");
+        }
+
+        // split binds each operand by the space on its side, whereas max binds both by the wider space
+        assert_eq!(parse_asymmetric("2 * 3+ 4", Asymmetry::Split).unwrap(), "(2 * (3 + 4))");
+        assert_eq!(parse_asymmetric("2 * 3+ 4", Asymmetry::Max).unwrap(), "((2 * 3) + 4)");
+        assert_eq!(parse_asymmetric("1 +2 * 3", Asymmetry::Split).unwrap(), "((1 + 2) * 3)");
+        assert_eq!(parse_asymmetric("1 +2 * 3", Asymmetry::Max).unwrap(), "(1 + (2 * 3))");
+        assert_eq!(parse_asymmetric("1+ 2 * 3", Asymmetry::Max).unwrap(), "(1 + (2 * 3))");
+        assert_eq!(parse_asymmetric("1  +2 * 3", Asymmetry::Max).unwrap(), "(1 + (2 * 3))");
+
+        let reject = |input| parse_asymmetric(input, Asymmetry::Reject);
+        assert_eq!(reject("1 + 2"), Ok("(1 + 2)".into()));
+        assert_eq!(reject("1 * -2"), Ok("(1 * (- 2))".into()));
+        assert_eq!(reject("1 +2"), Err(ParseError::AsymmetricSpacing("+".into(), 2)));
+        assert_eq!(reject("1+ 2"), Err(ParseError::AsymmetricSpacing("+".into(), 1)));
+        assert_eq!(reject("1 + 2*3  ^ 4"), Err(ParseError::AsymmetricSpacing("^".into(), 9)));
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        let options = ParseOptions {
+            implicit_multiplication: true, 
+            ..Default::default()
+        };
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("2 3", "(2 * 3)");
+        assert_parse("2 3 4", "((2 * 3) * 4)");
+        assert_parse("2 sqrt 4", "(2 * (sqrt 4))");
+        assert_parse("sqrt 2", "(sqrt 2)");
+        assert_parse("sqrt 2 3", "((sqrt 2) * 3)");
+        assert_parse("2(3+4)", "(2 * (3 + 4))");
+        assert_parse("2 (3 + 4)", "(2 * (3 + 4))");
+        assert_parse("(1 + 2)(3 + 4)", "((1 + 2) * (3 + 4))");
+        assert_parse("2 3+4", "(2 * (3 + 4))");
+        assert_parse("2 3 + 4", "((2 * 3) + 4)");
+        assert_parse("1 + 2 3", "(1 + (2 * 3))");
+        assert_parse("2 - 3", "(2 - 3)");
+        assert_parse("2 x", "(2 * x)");
+        assert_parse("2x y", "((2 * x) * y)");
+        assert_eq!(parse("2 3").unwrap_err(), ParseError::TrailingInput("3".into(), 2));
+    }
+
+    #[test]
+    fn custom_precedence() {
+        let mut options = ParseOptions::default();
+        options.precedence.insert(BinOp::Mul, 2);
+        options.precedence.remove(&BinOp::Rem);
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("1 + 2 * 3", "((1 + 2) * 3)");
+        assert_parse("1 * 2 + 3", "((1 * 2) + 3)");
+        assert_parse("1 + 2 / 3", "(1 + (2 / 3))");
+        assert_parse("1 + 2*3", "(1 + (2 * 3))");
+        assert_eq!(parse_with("1 % 2", &options).unwrap_err(), ParseError::TrailingInput("%".into(), 2));
+    }
+
+    #[test]
+    fn associativity() {
+        assert_parse("2 - 3 - 4", "((2 - 3) - 4)");
+        assert_parse("1*1 - 2*2 - 3*3", "(((1 * 1) - (2 * 2)) - (3 * 3))");
+        assert_parse("1 - 2*2 - 3", "((1 - (2 * 2)) - 3)");
+        assert_parse("1 / 2^2 / 3 + 4", "(((1 / (2 ^ 2)) / 3) + 4)");
+        assert_parse("2 ^ 3*4 ^ 5", "(2 ^ ((3 * 4) ^ 5))");
+        assert_parse("2 / 3 / 4", "((2 / 3) / 4)");
+        assert_parse("2 ^ 3 ^ 4", "(2 ^ (3 ^ 4))");
+
+        let mut options = ParseOptions::default();
+        options.associativity.insert(BinOp::Sub, Associativity::Right);
+        options.associativity.insert(BinOp::Pow, Associativity::Left);
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("2 - 3 - 4", "(2 - (3 - 4))");
+        assert_parse("2 - 3 + 4", "(2 - (3 + 4))");
+        assert_parse("2 + 3 - 4 - 5", "((2 + 3) - (4 - 5))");
+        assert_parse("2-3 - 4-5", "((2 - 3) - (4 - 5))");
+        assert_parse("2 ^ 3 ^ 4", "((2 ^ 3) ^ 4)");
+        assert_parse("2^3^4", "((2 ^ 3) ^ 4)");
+    }
+
+    #[test]
+    fn tab_width() {
+        assert_parse("1 *\t2  + 3", "((1 * 2) + 3)");
+        assert_parse("1 * 2  + 3", "((1 * 2) + 3)");
+
+        let mut options = ParseOptions::default();
+        options.lex.tab_width = 4;
+        let expr = parse_with("1 *\t2  + 3", &options).unwrap();
+        assert_eq!(format!("{expr}"), "(1 * (2 + 3))");
+    }
+
+    #[test]
+    fn max_spacing() {
+        assert_parse("1   *   2  + 3", "(1 * (2 + 3))");
+        assert_parse("1 *                                        2  + 3", "(1 * (2 + 3))");
+
+        let mut options = ParseOptions::default();
+        options.lex.max_spacing = Some(2);
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("1   *   2  + 3", "((1 * 2) + 3)");
+        assert_parse("1 *                                        2  + 3", "((1 * 2) + 3)");
+        assert_parse("1 * 2+3", "(1 * (2 + 3))");
+
+        let mut options = ParseOptions::default();
+        options.lex.tab_width = usize::MAX;
+        let expr = parse_with("1 *\t\t2 + 3", &options).unwrap();
+        assert_eq!(format!("{expr}"), "(1 * (2 + 3))");
+    }
+
+    #[test]
+    fn precedence_order() {
+        let options = ParseOptions {
+            precedence_order: PrecedenceOrder::AlgebraicFirst, 
+            ..ParseOptions::default()
+        };
+        let assert_orders = |input: &str, spacing_first: &str, algebraic_first: &str| {
+            assert_eq!(parse(input).unwrap().to_string(), spacing_first);
+            assert_eq!(parse_with(input, &options).unwrap().to_string(), algebraic_first);
+        };
+        assert_orders("1 * 2+3", "(1 * (2 + 3))", "((1 * 2) + 3)");
+        assert_orders("1*2 ^ 3", "((1 * 2) ^ 3)", "(1 * (2 ^ 3))");
+        assert_orders("1 + 2 * 3", "(1 + (2 * 3))", "(1 + (2 * 3))");
+        // operators of the same algebraic precedence still group by spacing
+        assert_orders("1 - 2-3", "(1 - (2 - 3))", "(1 - (2 - 3))");
+        assert_orders("1-2 + 3*4  *  5", "(((1 - 2) + (3 * 4)) * 5)", "((1 - 2) + ((3 * 4) * 5))");
+
+        let a = Precedence { spacing: 0, algebraic: 2 };
+        let b = Precedence { spacing: 1, algebraic: 1 };
+        assert_eq!(a.cmp_in(&b, PrecedenceOrder::SpacingFirst), Ordering::Greater);
+        assert_eq!(a.cmp_in(&b, PrecedenceOrder::AlgebraicFirst), Ordering::Less);
+        assert_eq!(a.partial_cmp(&b), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn normalize_spacing() {
+        let options = ParseOptions {
+            normalize_spacing: true, 
+            ..ParseOptions::default()
+        };
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("1 *    2 + 3", "((1 * 2) + 3)");
+        assert_parse("1 * 2+3", "((1 * 2) + 3)");
+        assert_parse("1 - 2  -  3", "((1 - 2) - 3)");
+        assert_parse("2 ^  3 ^ 2", "(2 ^ (3 ^ 2))");
+        assert_parse("sqrt  4 + 5", "((sqrt 4) + 5)");
+        assert_parse("-  2 ^ 2", "(- (2 ^ 2))");
+        assert_parse("(1 + 2)   *   3", "((1 + 2) * 3)");
+        assert_parse("1 < 2 ?  3 : 4  +  5", "((1 < 2) ? 3 : (4 + 5))");
+    }
+
+    #[test]
+    fn statements() {
+        let expr = parse("x = 3; y = x + 1; y * 2").unwrap();
+        assert_eq!(expr, Ast::Seq(vec![
+            Ast::assign("x", Ast::literal(3.0)), 
+            Ast::assign("y", Ast::binary(BinOp::Add, Ast::variable("x"), Ast::literal(1.0))), 
+            Ast::binary(BinOp::Mul, Ast::variable("y"), Ast::literal(2.0)), 
+        ]));
+        assert_eq!(format!("{expr}"), "x = 3; y = (x + 1); (y * 2)");
+        assert_eq!(format!("{}", parse("x = 1 * 2+3").unwrap()), "x = (1 * (2 + 3))");
+        assert_eq!(format!("{}", parse("1;2").unwrap()), "1; 2");
+
+        assert_eq!(parse("3 = x").unwrap_err(), ParseError::InvalidAssignment(2));
+        assert_eq!(parse("x + 1 = 2").unwrap_err(), ParseError::InvalidAssignment(6));
+        assert_eq!(parse("pi = 3").unwrap_err(), ParseError::InvalidAssignment(3));
+        assert_eq!(parse("x = y = 1").unwrap_err(), ParseError::TrailingInput("=".into(), 6));
+        assert_eq!(parse("(x = 1)").unwrap_err(), ParseError::UnexpectedToken("=".into(), 3));
+        assert_eq!(parse("x =").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("1;").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("; 1").unwrap_err(), ParseError::UnexpectedToken(";".into(), 0));
+    }
+
+    #[test]
+    fn prefix() {
+        let (expr, rest) = parse_prefix("1+2 3+4").unwrap();
+        assert_eq!(expr, parse("1 + 2").unwrap());
+        assert_eq!(rest, " 3+4");
+        let (expr, rest) = parse_prefix(rest).unwrap();
+        assert_eq!(expr, parse("3 + 4").unwrap());
+        assert_eq!(rest, "");
+
+        let mut rest = "  1 * 2+3 sqrt  4 x = 5 (1) -2";
+        let mut exprs = Vec::new();
+        while let Some((expr, tail)) = parse_prefix(rest) {
+            exprs.push(expr.to_string());
+            rest = tail;
+        }
+        assert_eq!(exprs, ["(1 * (2 + 3))", "(sqrt 4)", "x = 5", "(1 - 2)"]);
+        assert_eq!(rest, "");
+
+        // the rest is returned as is, even if it isn't valid
+        assert_eq!(parse_prefix("1; 2").unwrap().1, "; 2");
+        assert_eq!(parse_prefix("1 /* c */ )").unwrap().1, " /* c */ )");
+        assert_eq!(parse_prefix("1 2e").unwrap().1, " 2e");
+        assert_eq!(parse_prefix("1 +"), None);
+        assert_eq!(parse_prefix(") 1"), None);
+        assert_eq!(parse_prefix("  "), None);
+
+        let options = ParseOptions {
+            implicit_multiplication: true, 
+            ..ParseOptions::default()
+        };
+        assert_eq!(parse_prefix_with("2 3, 4", &options).unwrap().1, ", 4");
+    }
+
+    #[test]
+    fn bindings() {
+        let expr = parse("let x = 2 in x * x").unwrap();
+        let square = Ast::binary(BinOp::Mul, Ast::variable("x"), Ast::variable("x"));
+        assert_eq!(expr, Ast::binding("x", Ast::literal(2.0), square));
+        assert_parse("let x = 1+2 in x * 3", "(let x = (1 + 2) in (x * 3))");
+        assert_parse("let x = 1 in let x = 2 in x", "(let x = 1 in (let x = 2 in x))");
+        assert_parse("let x = let y = 1 in y in x", "(let x = (let y = 1 in y) in x)");
+        assert_parse("2 * let x = 1 in x + 1", "(2 * (let x = 1 in (x + 1)))");
+        assert_parse("1 ? let x = 2 in x : 3", "(1 ? (let x = 2 in x) : 3)");
+        assert_eq!(parse("(let x = 1 in x) * 2").unwrap().to_infix(), "(let x = 1 in x) * 2");
+        assert_eq!(format!("{:#}", parse("let x = 1 + 2 in x").unwrap()), "(let x=(1+2) in x)");
+
+        let options = ParseOptions {
+            implicit_multiplication: true, 
+            ..ParseOptions::default()
+        };
+        let expr = parse_with("let x = 2 x in x", &options).unwrap();
+        assert_eq!(format!("{expr}"), "(let x = (2 * x) in x)");
+
+        assert_eq!(parse("let x = 1").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("let x = 1; x").unwrap_err(), ParseError::UnexpectedToken(";".into(), 9));
+        assert_eq!(parse("let x in x").unwrap_err(), ParseError::UnexpectedToken("in".into(), 6));
+        assert_eq!(parse("let 1 = 2 in 3").unwrap_err(), ParseError::UnexpectedToken("1".into(), 4));
+        assert_eq!(parse("let in = 1 in 2").unwrap_err(), ParseError::UnexpectedToken("in".into(), 4));
+        assert_eq!(parse("1 in 2").unwrap_err(), ParseError::TrailingInput("in".into(), 2));
+        assert_eq!(parse("in").unwrap_err(), ParseError::UnexpectedToken("in".into(), 0));
+    }
+
+    #[test]
+    fn speculative() {
+        // a failed parse from a snapshot leaves the original tokens where they were
+        let options = ParseOptions::default();
+        let mut tokens = Tokens::from("1 + 2 = 3");
+        let mut snapshot = tokens.snapshot();
+        assert_eq!(
+            parse_statement(&mut snapshot, &options, &|_| {}), 
+            Err(ParseError::InvalidAssignment(6)), 
+        );
+        let expr = parse_expression(&mut tokens, Precedence::MIN, 0, &options, &|_| {}).unwrap();
+        assert_eq!(format!("{expr}"), "(1 + 2)");
+        assert!(matches!(tokens.next(), Some(Token::Symbol("=", ..))));
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        assert_eq!(parse("1 < 2 < 3").unwrap_err(), ParseError::ChainedComparison("<".into(), 6));
+        assert_eq!(parse("1<2>=0").unwrap_err(), ParseError::ChainedComparison(">=".into(), 3));
+        assert_eq!(parse("x < 1 + 2 < y").unwrap_err(), ParseError::ChainedComparison("<".into(), 10));
+        assert_eq!(format!("{}", parse("(1 < 2) < 3").unwrap()), "((1 < 2) < 3)");
+        assert_eq!(format!("{}", parse("1 < 2  <  3").unwrap()), "((1 < 2) < 3)");
+        assert_eq!(format!("{}", parse("1  <  2 < 3").unwrap()), "(1 < (2 < 3))");
+        assert_eq!(format!("{}", parse("1 < 2 ? 3 : 4 < 5").unwrap()), "((1 < 2) ? 3 : (4 < 5))");
+
+        let options = ParseOptions { chaining: Chaining::Conjunction, ..ParseOptions::default() };
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("1 < 2 < 3", "((1 < 2) ? (2 < 3) : 0)");
+        assert_parse("a < b <= c > d", "(((a < b) ? (b <= c) : 0) ? (c > d) : 0)");
+        assert_parse("1 < 2  <  3", "((1 < 2) < 3)");
+    }
+
+    #[test]
+    fn percent() {
+        let options = ParseOptions { percent: true, ..ParseOptions::default() };
+        let assert_parse = |input: &str, expected: &str| {
+            let expr = parse_with(input, &options).unwrap();
+            assert_eq!(format!("{expr}"), expected);
+        };
+        assert_parse("50%", "(50%)");
+        assert_parse("200 + 10%", "(200 + (10%))");
+        assert_parse("7 % 3", "(7 % 3)");
+        assert_parse("7%3", "(7 % 3)");
+        assert_parse("7 %3", "(7 % 3)");
+        assert_parse("x% * 2", "((x%) * 2)");
+        assert_parse("(50%)", "(50%)");
+        assert_parse("5!%", "((5 !)%)");
+        assert_parse("1 + 2%", "(1 + (2%))");
+        assert_parse("1 +2%", "(1 + (2%))");
+        // a spaced `%` is a modulo, which is missing its rhs
+        assert_eq!(parse_with("50 %", &options).unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("50%").unwrap_err(), ParseError::UnexpectedEof);
+    }
+
+    #[test]
+    fn leading_whitespace() {
+        for input in ["1 + 2", "  -1*2 + 3", "sqrt  1 + 2", "(1 + 2)"] {
+            for leading in [" ", "  ", "\t", "     "] {
+                assert_eq!(parse(&format!("{leading}{input}")), parse(input));
+            }
+        }
+    }
+
+    #[test]
+    fn radix_prefixes() {
+        assert_parse("0xff + 0b1010", "(255 + 10)");
+        assert_parse("0o10 * 2", "(8 * 2)");
+        assert_eq!(parse("0xff * 2").unwrap().to_source(), "0xff * 2");
+    }
+
+    #[test]
+    fn unicode_operators() {
+        let assert_alias = |input: &str, ascii: &str| {
+            assert_eq!(parse(input).unwrap(), parse(ascii).unwrap());
+            assert_eq!(parse(input).unwrap().to_string(), parse(ascii).unwrap().to_string());
+        };
+        assert_alias("2 × 3 − 1", "2 * 3 - 1");
+        assert_alias("6 ÷ 2", "6 / 2");
+        assert_alias("2 × 3−1", "2 * 3-1");
+        assert_alias("−2 × −x", "-2 * -x");
+        assert_parse("6 ÷ 2 × 3", "((6 / 2) * 3)");
+    }
+
+    #[test]
+    fn root_symbol() {
+        let assert_alias = |input: &str, word: &str| {
+            assert_eq!(parse(input).unwrap(), parse(word).unwrap());
+            assert_eq!(parse(input).unwrap().to_string(), parse(word).unwrap().to_string());
+        };
+        assert_alias("√4 + 1", "sqrt 4 + 1");
+        assert_alias("√  4 + 1", "sqrt  4 + 1");
+        assert_alias("2 * √x", "2 * sqrt x");
+        assert_alias("√√16", "sqrt sqrt 16");
+        assert_parse("√4 + 1", "((sqrt 4) + 1)");
+        assert_parse("√  4 + 1", "(sqrt (4 + 1))");
+        assert_parse("√(4 + 5)", "(sqrt (4 + 5))");
+
+        let options = ParseOptions {
+            implicit_multiplication: true, 
+            ..Default::default()
+        };
+        assert_eq!(format!("{}", parse_with("2√4", &options).unwrap()), "(2 * (sqrt 4))");
+        assert_eq!(parse("√").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("4√").unwrap_err(), ParseError::TrailingInput("√".into(), 1));
+    }
+
+    #[test]
+    fn double_star() {
+        assert_eq!(parse("2 ** 3").unwrap(), parse("2 ^ 3").unwrap());
+        assert_parse("2 ** 3 ** 2", "(2 ^ (3 ^ 2))");
+        assert_parse("2 * 3**2", "(2 * (3 ^ 2))");
+        assert_parse("-2 ** 2", "((- 2) ^ 2)");
+        assert_eq!(parse("2 * *3").unwrap_err(), ParseError::UnexpectedToken("*".into(), 4));
+        assert_eq!(parse("2 ** *3").unwrap_err(), ParseError::UnexpectedToken("*".into(), 5));
+    }
+
+    #[test]
+    fn comments() {
+        assert_parse("1 + 2 # ignored", "(1 + 2)");
+        assert_parse("1 /* x */ + 2", "(1 + 2)");
+        assert_parse("1 * 2 /* x */ + 3", "((1 * 2) + 3)");
+        assert_parse("1 * 2/* x */+3", "(1 * (2 + 3))");
+        assert_parse("# sum\n1 +\n# of two\n2", "(1 + 2)");
+        assert_eq!(parse("1 + /* 2").unwrap_err(), ParseError::Lex(LexError::UnterminatedComment));
+        assert_eq!(parse("# comment only").unwrap_err(), ParseError::EmptyInput);
+        assert_eq!(parse(" /* a */ # b\n").unwrap_err(), ParseError::EmptyInput);
+        assert_eq!(parse("/* a").unwrap_err(), ParseError::Lex(LexError::UnterminatedComment));
+    }
+
+    #[test]
+    fn from_str() {
+        let expr: Ast = "1 * 2+3".parse().unwrap();
+        assert_eq!(expr, parse("1 * 2+3").unwrap());
+        assert_eq!("sqrt x".parse::<Ast>().unwrap().to_string(), "(sqrt x)");
+        let err: ParseError = "1 +".parse::<Ast>().unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedEof);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("1 +").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("").unwrap_err(), ParseError::EmptyInput);
+        assert_eq!(parse("   ").unwrap_err(), ParseError::EmptyInput);
+        assert_eq!(parse("\n\t").unwrap_err(), ParseError::EmptyInput);
+        assert_eq!(parse("* 2").unwrap_err(), ParseError::UnexpectedToken("*".into(), 0));
+        assert_eq!(parse("1 + ,").unwrap_err(), ParseError::UnexpectedToken(",".into(), 4));
+        assert_eq!(parse("sinh 1").unwrap_err(), ParseError::TrailingInput("1".into(), 5));
+        assert_eq!(parse("sqrt").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("1 2").unwrap_err(), ParseError::TrailingInput("2".into(), 2));
+        assert_eq!(parse("(1 + 2").unwrap_err(), ParseError::UnexpectedEof);
+        assert_eq!(parse("(1 + 2 3").unwrap_err(), ParseError::UnexpectedToken("3".into(), 7));
+        assert_eq!(parse(")").unwrap_err(), ParseError::UnexpectedToken(")".into(), 0));
+        assert_eq!(parse("1 + 2)").unwrap_err(), ParseError::TrailingInput(")".into(), 5));
+        assert_eq!(parse("1 + 2 3").unwrap_err(), ParseError::TrailingInput("3".into(), 6));
+        assert_eq!(parse("π 3").unwrap_err(), ParseError::TrailingInput("3".into(), 3));
+        assert_eq!(parse("÷ 3").unwrap_err(), ParseError::UnexpectedToken("/".into(), 0));
+        assert_eq!(parse("1 + €").unwrap_err(), ParseError::UnexpectedToken("€".into(), 4));
+
+        let invalid_number = |lexeme: &str| ParseError::Lex(LexError::InvalidNumber(lexeme.into()));
+        assert_eq!(parse("1e").unwrap_err(), invalid_number("1e"));
+        assert_eq!(parse("1 + 2e").unwrap_err(), invalid_number("2e"));
+        assert_eq!(parse("1 2e").unwrap_err(), invalid_number("2e"));
+        assert_eq!(parse("(1 2e").unwrap_err(), invalid_number("2e"));
+        assert_eq!(parse("1.2.3").unwrap_err(), invalid_number("1.2.3"));
+        assert_eq!(parse(".").unwrap_err(), invalid_number("."));
+        assert_eq!(parse("1 + ..").unwrap_err(), invalid_number(".."));
+        assert_eq!(parse("1..2").unwrap_err(), invalid_number("1..2"));
+        assert_eq!(parse("0x + 1").unwrap_err(), invalid_number("0x"));
+        assert_eq!(parse("1 + 0xZ").unwrap_err(), invalid_number("0xZ"));
+    }
+
+    #[test]
+    fn render() {
+        let render = |input: &str| render_error(input, &parse(input).unwrap_err());
+        assert_eq!(render("1 + * 2"), "error: unexpected token `*`\n  1 + * 2\n      ^");
+        let trailing = "error: unexpected trailing input starting at `3`";
+        assert_eq!(render("1 + 2 3"), format!("{trailing}\n  1 + 2 3\n        ^"));
+        assert_eq!(render("π 3"), format!("{trailing}\n  π 3\n    ^"));
+        assert_eq!(render("1 +\t2 3"), format!("{trailing}\n  1 +\t2 3\n     \t  ^"));
+        assert_eq!(render("1 +\n2 3\n"), format!("{trailing}\n  2 3\n    ^"));
+        assert_eq!(render("max(1, sqrt)"), "error: unexpected token `)`\n  max(1, sqrt)\n             ^");
+        assert_eq!(render("1 + \n"), "error: unexpected end of input\n  1 + \n     ^");
+        assert_eq!(render("1 + 2e"), "error: invalid number `2e`");
+
+        let options = ParseOptions { asymmetry: Asymmetry::Reject, ..ParseOptions::default() };
+        let input = "1 +  2";
+        let err = parse_with(input, &options).unwrap_err();
+        assert_eq!(render_error(input, &err), format!("error: {err}\n  1 +  2\n    ^"));
+    }
+
+    #[test]
+    fn trace() {
+        let traces = RefCell::new(Vec::new());
+        let expr = parse_traced("sqrt sqrt  1 + 1", &ParseOptions::default(), &|trace| {
+            traces.borrow_mut().push(trace.clone())
+        });
+        assert_eq!(format!("{}", expr.unwrap()), "(sqrt (sqrt (1 + 1)))");
+        // the inner `sqrt` is spaced wider from its argument, so it takes the `+` before the outer one can
+        let arg = |spacing| Precedence { spacing, algebraic: 0 };
+        assert_eq!(traces.into_inner(), [
+            Trace::Prefix { op: UnOp::Sqrt, min: arg(1), depth: 0 }, 
+            Trace::Prefix { op: UnOp::Sqrt, min: arg(2), depth: 1 }, 
+            Trace::Binary {
+                op: BinOp::Add, 
+                precedence: Precedence { spacing: 1, algebraic: 2 }, 
+                min: arg(2), 
+                depth: 2, 
+            }, 
+        ]);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = |open: &str, depth: usize, close: &str| {
+            format!("{}1{}", open.repeat(depth), close.repeat(depth))
+        };
+        assert!(parse(&nested("(", 200, ")")).is_ok());
+        assert!(parse(&nested("sqrt ", 200, "")).is_ok());
+        for input in [nested("(", 10_000, ")"), nested("sqrt ", 10_000, ""), nested("-", 10_000, "")] {
+            assert_eq!(parse(&input).unwrap_err(), ParseError::DepthLimitExceeded);
+        }
+        assert_eq!(parse(&nested("2 ^ ", 10_000, "")).unwrap_err(), ParseError::DepthLimitExceeded);
+        assert!(parse(&nested("2 - ", 10_000, "")).is_ok());
+
+        let options = ParseOptions {
+            max_depth: 2, 
+            ..ParseOptions::default()
+        };
+        assert!(parse_with("((1))", &options).is_ok());
+        assert_eq!(parse_with("(((1)))", &options).unwrap_err(), ParseError::DepthLimitExceeded);
+        assert!(parse_with("1 ^ 2 ^ 3 ^ 4", &options).is_ok());
+        assert_eq!(parse_with("1 ^ 2 ^ 3 ^ 4 ^ 5", &options).unwrap_err(), ParseError::DepthLimitExceeded);
+    }
+
+    #[test]
+    fn token_limit() {
+        let options = ParseOptions {
+            max_tokens: Some(1_000), 
+            ..ParseOptions::default()
+        };
+        // a sum of n terms has 2n - 1 tokens
+        let sum = |terms: usize| vec!["1"; terms].join(" + ");
+        assert!(parse_with(&sum(500), &options).is_ok());
+        assert_eq!(parse_with(&sum(501), &options).unwrap_err(), ParseError::TooManyTokens);
+        assert_eq!(parse_with(&sum(100_000), &options).unwrap_err(), ParseError::TooManyTokens);
+        assert!(parse(&sum(501)).is_ok());
+
+        // comments and whitespace aren't tokens
+        let options = ParseOptions { max_tokens: Some(3), ..ParseOptions::default() };
+        assert!(parse_with("1 + /* 2 + */ 3   # 4", &options).is_ok());
+        assert_eq!(parse_with("(1)", &options), Ok(Ast::literal(1.0)));
+        assert_eq!(parse_with("(1))", &options).unwrap_err(), ParseError::TooManyTokens);
+    }
+
+    #[test]
+    fn literal_range() {
+        let options = ParseOptions {
+            max_literal: Some(1000.0), 
+            ..ParseOptions::default()
+        };
+        let out_of_range = |lexeme: &str, offset| ParseError::LiteralOutOfRange(lexeme.into(), offset);
+        assert_eq!(parse_with("5000", &options).unwrap_err(), out_of_range("5000", 0));
+        assert_eq!(parse_with("999", &options), Ok(Ast::literal(999.0)));
+        assert!(parse_with("1000 + 999.5 * pi", &options).is_ok());
+        assert_eq!(parse_with("1 + -5e3", &options).unwrap_err(), out_of_range("5e3", 5));
+        assert_eq!(parse_with("0x3e9", &options).unwrap_err(), out_of_range("0x3e9", 0));
+        assert_eq!(parse_with("2 * inf", &options).unwrap_err(), out_of_range("inf", 4));
+        assert!(parse("5000").is_ok());
+
+        // the bound applies to each literal rather than to the values they compute
+        assert!(parse_with("999 * 999", &options).is_ok());
+        let input = "1 + 1001";
+        let err = parse_with(input, &options).unwrap_err();
+        let rendered = render_error(input, &err);
+        assert_eq!(rendered, "error: literal `1001` is out of range\n  1 + 1001\n      ^^^^");
+    }
+
+    /// Generates valid expression strings over the whole operator set with random spacing. Leaves are small
+    /// integers, decimals and variables, which shrink towards `0` and towards plain binary operations
+    fn expression() -> impl Strategy<Value = String> {
+        let number = prop_oneof![
+            (0u32..1000).prop_map(|x| x.to_string()), 
+            (0u32..1000, 0u32..100).prop_map(|(x, y)| format!("{x}.{y}")), 
+        ];
+        let variable = prop::sample::select(&["x", "y", "z"][..]).prop_map(String::from);
+        let leaf = prop_oneof![number, variable];
+        leaf.prop_recursive(4, 32, 3, |operand| {
+            let space = || "[ ]{0,3}";
+            let binary = prop::sample::select(BinOp::ALL).prop_map(BinOp::symbol);
+            let function = || prop::sample::select(FUNCTIONS);
+            prop_oneof![
+                (operand.clone(), space(), binary, space(), operand.clone())
+                    .prop_map(|(lhs, s1, op, s2, rhs)| format!("{lhs}{s1}{op}{s2}{rhs}")), 
+                ("[-+]", space(), operand.clone()).prop_map(|(sign, s, x)| format!("{sign}{s}{x}")), 
+                // a function directly followed by parentheses is a call, so there's always a space
+                (function(), "[ ]{1,3}", operand.clone()).prop_map(|(name, s, x)| format!("{name}{s}{x}")), 
+                (function(), operand.clone()).prop_map(|(name, x)| format!("{name}({x})")), 
+                (operand.clone(), space()).prop_map(|(x, s)| format!("{x}{s}!")), 
+                operand.clone().prop_map(|x| format!("({x})")), 
+                (operand.clone(), operand.clone()).prop_map(|(a, b)| format!("max({a}, {b})")), 
+                (operand.clone(), operand.clone(), operand)
+                    .prop_map(|(cond, then, otherwise)| format!("{cond}  ?  {then} : {otherwise}")), 
+            ]
+        })
+    }
+
+    // generated comparisons may be chained, which are otherwise rejected
+    fn chaining() -> ParseOptions {
+        ParseOptions { chaining: Chaining::Conjunction, ..ParseOptions::default() }
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trip(input in expression()) {
+            let expr = parse_with(&input, &chaining()).unwrap();
+            prop_assert_eq!(parse_with(&expr.to_string(), &chaining()).unwrap(), expr);
+        }
+
+        #[test]
+        fn infix_round_trip(input in expression()) {
+            let expr = parse_with(&input, &chaining()).unwrap();
+            prop_assert_eq!(parse_with(&expr.to_infix(), &chaining()).unwrap(), expr);
+        }
+
+        #[test]
+        fn canonical_round_trip(input in expression()) {
+            let expr = parse_with(&input, &chaining()).unwrap();
+            let canonical = expr.to_canonical();
+            prop_assert_eq!(parse_with(&canonical, &chaining()).unwrap(), expr.clone());
+            prop_assert_eq!(parse_with(&canonical, &chaining()).unwrap().to_canonical(), canonical);
+        }
+    }
+}
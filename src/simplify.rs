@@ -0,0 +1,196 @@
+use crate::{eval, Ast, BinOp, Number, NumberKind, UnOp};
+
+/// Simplifies an AST bottom-up by folding constant sub-trees into literals and applying the identities
+/// `x + 0`, `x - 0`, `x * 1`, `x / 1`, and `x * 0`. Sub-trees that fail to evaluate, such as a division by
+/// zero, are left unfolded; note however that `x * 0` discards `x` regardless of whether it can be evaluated.
+/// Like folded literals, the `0` is a float if either operand contains a float literal
+pub fn simplify(ast: Ast) -> Ast {
+    let ast = match ast {
+        Ast::Literal(_) | Ast::Variable(_) => return ast, 
+        Ast::Unary { op, operand } => Ast::unary(op, simplify(*operand)), 
+        Ast::Binary { op, lhs, rhs, .. } => {
+            let x = simplify(*lhs);
+            let y = simplify(*rhs);
+            match op {
+                BinOp::Add if is_literal(&x, 0.0) => return y, 
+                BinOp::Add | BinOp::Sub if is_literal(&y, 0.0) => return x, 
+                BinOp::Mul if is_literal(&x, 1.0) => return y, 
+                BinOp::Mul | BinOp::Div if is_literal(&y, 1.0) => return x, 
+                BinOp::Mul if is_literal(&x, 0.0) || is_literal(&y, 0.0) => {
+                    return literal(0.0, has_float(&x) || has_float(&y))
+                }
+                _ => Ast::binary(op, x, y), 
+            }
+        }
+        Ast::Call(name, args) => Ast::Call(name, args.into_iter().map(simplify).collect()), 
+        // a constant condition selects its branch. the branches are otherwise simplified separately, since
+        // only one of them is ever evaluated
+        Ast::Ternary(cond, then, otherwise) => match simplify(*cond) {
+            Ast::Literal(number) if number.value != 0.0 => return simplify(*then), 
+            Ast::Literal(_) => return simplify(*otherwise), 
+            cond => Ast::ternary(cond, simplify(*then), simplify(*otherwise)), 
+        }, 
+        Ast::Assign(name, value) => return Ast::assign(name, simplify(*value)), 
+        Ast::Seq(statements) => return Ast::Seq(statements.into_iter().map(simplify).collect()), 
+        Ast::Let { name, value, body } => return Ast::binding(name, simplify(*value), simplify(*body)), 
+    };
+    fold(ast)
+}
+
+/// Rewrites negations bottom-up without folding anything else: a double negation `-(-x)` becomes `x`, and
+/// the negation of a literal becomes a negative literal, e.g. `- - -5` becomes `-5`. Unlike `simplify`, this
+/// never evaluates an operation, such that grouping and non-constant sub-trees are otherwise kept as parsed
+pub fn simplify_negations(ast: Ast) -> Ast {
+    match ast {
+        Ast::Literal(_) | Ast::Variable(_) => ast, 
+        Ast::Unary { op: UnOp::Neg, operand } => match simplify_negations(*operand) {
+            Ast::Unary { op: UnOp::Neg, operand } => *operand, 
+            Ast::Literal(number) => Ast::Literal(Number {
+                value: -number.value, 
+                kind: number.kind, 
+                lexeme: None, 
+            }), 
+            operand => Ast::unary(UnOp::Neg, operand), 
+        }, 
+        Ast::Unary { op, operand } => Ast::unary(op, simplify_negations(*operand)), 
+        Ast::Binary { op, lhs, rhs, .. } => {
+            Ast::binary(op, simplify_negations(*lhs), simplify_negations(*rhs))
+        }
+        Ast::Call(name, args) => Ast::Call(name, args.into_iter().map(simplify_negations).collect()), 
+        Ast::Ternary(cond, then, otherwise) => Ast::ternary(
+            simplify_negations(*cond), 
+            simplify_negations(*then), 
+            simplify_negations(*otherwise), 
+        ), 
+        Ast::Assign(name, value) => Ast::assign(name, simplify_negations(*value)), 
+        Ast::Seq(statements) => Ast::Seq(statements.into_iter().map(simplify_negations).collect()), 
+        Ast::Let { name, value, body } => {
+            Ast::binding(name, simplify_negations(*value), simplify_negations(*body))
+        }
+    }
+}
+
+/// Folds an operation whose operands are all literals into a single literal. Operations that fail to evaluate
+/// are returned as-is. The result is an integer if all operands are and its value is integral, e.g. `6 / 3`
+/// folds to `2` but `7 / 2` to `3.5`, and a float if any operand is, e.g. `2.0 + 3` folds to `5.0`
+fn fold(ast: Ast) -> Ast {
+    let constant = match &ast {
+        Ast::Literal(_) => true, 
+        Ast::Variable(_) => false, 
+        Ast::Unary { operand, .. } => matches!(**operand, Ast::Literal(_)), 
+        Ast::Binary { lhs, rhs, .. } => matches!((&**lhs, &**rhs), (Ast::Literal(_), Ast::Literal(_))), 
+        Ast::Call(_, args) => args.iter().all(|arg| matches!(arg, Ast::Literal(_))), 
+        Ast::Ternary(..) | Ast::Assign(..) | Ast::Seq(_) | Ast::Let { .. } => false, 
+    };
+    let Some(Ok(value)) = constant.then(|| eval(&ast)) else {
+        return ast
+    };
+    literal(value, has_float(&ast))
+}
+
+/// Creates the literal of a folded value, which is a float if `is_float`, and an integer if not and the value
+/// is integral
+fn literal(value: f64, is_float: bool) -> Ast {
+    match is_float {
+        true => Ast::Literal(Number { kind: NumberKind::Float, ..Number::from(value) }), 
+        false => Ast::literal(value), 
+    }
+}
+
+/// Checks whether the AST contains a float literal anywhere
+fn has_float(ast: &Ast) -> bool {
+    let mut is_float = false;
+    ast.visit(&mut |node| {
+        is_float |= matches!(node, Ast::Literal(Number { kind: NumberKind::Float, .. }));
+    });
+    is_float
+}
+
+/// Checks whether the AST is a literal with the given value
+fn is_literal(ast: &Ast, value: f64) -> bool {
+    matches!(ast, Ast::Literal(x) if x.value == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn assert_simplify(input: &str, expected: &str) {
+        let expr = simplify(parse(input).unwrap());
+        let output = format!("{expr}");
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(simplify(parse("1 + 2 * 3").unwrap()), Ast::Literal(7.0.into()));
+        assert_simplify("sqrt  2 + 2", "2");
+        assert_simplify("-(2 * 3)", "-6");
+        assert_simplify("1 / 0", "(1 / 0)");
+        assert_simplify("sqrt -1", "(sqrt -1)");
+        assert_simplify("(1 / 0) + 2 * 3", "((1 / 0) + 6)");
+        assert_simplify("max(1, 2 * 3)", "6");
+        assert_simplify("max(x, 2 * 3)", "max(x, 6)");
+        assert_simplify("max()", "max()");
+        assert_simplify("x = 1 + 2; x * 1", "x = 3; x");
+    }
+
+    #[test]
+    fn kinds() {
+        assert_simplify("2 + 3", "5");
+        assert_simplify("2.0 + 3", "5.0");
+        assert_simplify("2 * 1.5", "3.0");
+        assert_simplify("6 / 3", "2");
+        assert_simplify("7 / 2", "3.5");
+        assert_simplify("-2.0", "-2.0");
+        assert_simplify("1e3 - 1", "999.0");
+        assert_simplify("sqrt  2.0 * 8", "4.0");
+        assert_simplify("x * 0.0", "0.0");
+        assert_simplify("2.0 * 0", "0.0");
+        assert_simplify("0 * (x + 1.5)", "0.0");
+        assert_simplify("x * 0", "0");
+        assert_eq!(simplify(parse("2 + 3").unwrap()), Ast::literal(5.0));
+        assert_ne!(simplify(parse("2.0 + 3").unwrap()), Ast::literal(5.0));
+    }
+
+    #[test]
+    fn ternary() {
+        assert_simplify("1 < 2 ? x : 1 / 0", "x");
+        assert_simplify("0 ? x : y + 0", "y");
+        assert_simplify("x ? 1 + 2 : y", "(x ? 3 : y)");
+    }
+
+    #[test]
+    fn identities() {
+        assert_simplify("(1 / 0) + 0", "(1 / 0)");
+        assert_simplify("0 + (1 / 0)", "(1 / 0)");
+        assert_simplify("(1 / 0) - (1 - 1)", "(1 / 0)");
+        assert_simplify("(1 / 0) * 1", "(1 / 0)");
+        assert_simplify("1 * (1 / 0)", "(1 / 0)");
+        assert_simplify("(1 / 0) / 1", "(1 / 0)");
+        assert_simplify("(1 / 0) * 0", "0");
+        assert_simplify("0 - (1 / 0)", "(0 - (1 / 0))");
+        assert_simplify("x + 0", "x");
+        assert_simplify("0 + x", "x");
+        assert_simplify("x - 0", "x");
+        assert_simplify("x * 1", "x");
+        assert_simplify("(x + y) / 1", "(x + y)");
+        assert_simplify("x * 0", "0");
+        assert_simplify("0 * x", "0");
+        assert_simplify("x * (3 - 2) + y * (2 - 2)", "x");
+        assert_simplify("x + 1 + 2", "((x + 1) + 2)");
+    }
+
+    #[test]
+    fn negations() {
+        let negations = |input| simplify_negations(parse(input).unwrap());
+        assert_eq!(negations("--5"), Ast::literal(5.0));
+        assert_eq!(negations("-5"), Ast::literal(-5.0));
+        assert_eq!(negations("- - -5"), Ast::literal(-5.0));
+        assert_eq!(negations("--x"), Ast::variable("x"));
+        assert_eq!(negations("- - -x"), Ast::unary(UnOp::Neg, Ast::variable("x")));
+        assert_eq!(format!("{}", negations("1 - -2 * --(x + 0)")), "(1 - (-2 * (x + 0)))");
+        assert_eq!(format!("{}", negations("sqrt --4 + -(1 + 2)")), "((sqrt 4) + (- (1 + 2)))");
+    }
+}
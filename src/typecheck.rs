@@ -0,0 +1,200 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+use crate::{eval::scoped, Ast, BinOp, Env, NumberKind, UnOp};
+
+/// Reasons for which an AST doesn't typecheck
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeError {
+    /// The operands of an operator have different types, e.g. `1 + 2.0`. Contains the operator and the types
+    /// of its operands
+    Mismatch(String, NumberKind, NumberKind), 
+    /// An operator doesn't take operands of the type, e.g. the float functions such as `sqrt 4` and the
+    /// integer operators such as `~1.0`. Contains the operator and the type
+    Unsupported(String, NumberKind), 
+    /// The variable isn't assigned before it's used, so it has no type
+    UnboundVariable(String), 
+    /// The function has no known type, or isn't called with a number of arguments it accepts
+    UnknownOperator(String), 
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TypeError::Mismatch(op, lhs, rhs) => write!(f, "`{op}` can't combine {lhs} and {rhs}"), 
+            TypeError::Unsupported(op, kind) => write!(f, "`{op}` can't take {kind}"), 
+            TypeError::UnboundVariable(name) => write!(f, "unbound variable `{name}`"), 
+            TypeError::UnknownOperator(op) => write!(f, "unknown operator `{op}`"), 
+        }
+    }
+}
+
+impl core::error::Error for TypeError {}
+
+/// Computes the type of an expression under strict typing, where integers and floats never mix implicitly.
+/// The operands of a binary operator must have the same type, which is that of the result, except that
+/// comparisons are integers, and that `/` gives a float like `eval` does, e.g. `7 / 2` is `3.5`. Integer
+/// division is written `//` instead, e.g. `7 // 2` is `3`. Functions such as `sqrt` take and give floats,
+/// whereas factorials and the bitwise operators take and give integers. Percentages take either and give
+/// floats, e.g. `50%` is `0.5`. Types are converted explicitly with the functions `int` and
+/// `float`, which are parsed once added to `ParseOptions::functions`. Variables take the type of their last
+/// assignment, and must be assigned before they're used
+///
+/// ```
+/// # use space_precedence_parser::{parse, typecheck, NumberKind, TypeError};
+/// assert_eq!(typecheck(&parse("1 + 2").unwrap()), Ok(NumberKind::Int));
+/// assert_eq!(
+///     typecheck(&parse("1 + 2.0").unwrap()), 
+///     Err(TypeError::Mismatch("+".into(), NumberKind::Int, NumberKind::Float)), 
+/// );
+/// ```
+pub fn typecheck(ast: &Ast) -> Result<NumberKind, TypeError> {
+    typecheck_in(ast, &mut Env::new())
+}
+
+/// Implementation of `typecheck`, with the types of the variables assigned so far
+fn typecheck_in(ast: &Ast, env: &mut Env<NumberKind>) -> Result<NumberKind, TypeError> {
+    use NumberKind::{Float, Int};
+    let kind = match ast {
+        Ast::Literal(number) => number.kind, 
+        Ast::Variable(name) => *env
+            .get(name)
+            .ok_or_else(|| TypeError::UnboundVariable(name.clone()))?, 
+        Ast::Unary { op, operand } => {
+            let kind = typecheck_in(operand, env)?;
+            let accepts = match op {
+                UnOp::Neg | UnOp::Plus | UnOp::Abs => return Ok(kind), 
+                UnOp::Percent => return Ok(Float), 
+                UnOp::Function(name) if name == "int" => return Ok(Int), 
+                UnOp::Function(name) if name == "float" => return Ok(Float), 
+                UnOp::Function(name) => return Err(TypeError::UnknownOperator(name.clone())), 
+                UnOp::Fact | UnOp::Not => Int, 
+                UnOp::Sqrt
+                | UnOp::Sin
+                | UnOp::Cos
+                | UnOp::Tan
+                | UnOp::Ln
+                | UnOp::Log
+                | UnOp::Exp => Float, 
+            };
+            if kind != accepts {
+                return Err(TypeError::Unsupported(op.name().into(), kind))
+            }
+            kind
+        }
+        Ast::Binary { op, lhs, rhs, .. } => {
+            let (x, y) = (typecheck_in(lhs, env)?, typecheck_in(rhs, env)?);
+            if x != y {
+                return Err(TypeError::Mismatch(op.symbol().into(), x, y))
+            }
+            match op {
+                BinOp::BitAnd | BinOp::BitOr | BinOp::Shl | BinOp::Shr if x == Float => {
+                    return Err(TypeError::Unsupported(op.symbol().into(), x))
+                }
+                _ if op.is_comparison() => Int, 
+                BinOp::Div => Float, 
+                _ => x, 
+            }
+        }
+        Ast::Call(name, args) => {
+            let kinds = args.iter()
+                .map(|arg| typecheck_in(arg, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            match (name.as_str(), &kinds[..]) {
+                ("log", [Float] | [Float, Float]) => Float, 
+                ("log", [kind] | [_, kind]) => return Err(TypeError::Unsupported(name.clone(), *kind)), 
+                ("max" | "min", [first, rest @ ..]) => match rest.iter().find(|kind| *kind != first) {
+                    Some(other) => return Err(TypeError::Mismatch(name.clone(), *first, *other)), 
+                    None => *first, 
+                }
+                _ => return Err(TypeError::UnknownOperator(name.clone())), 
+            }
+        }
+        Ast::Ternary(cond, then, otherwise) => {
+            typecheck_in(cond, env)?;
+            let (x, y) = (typecheck_in(then, env)?, typecheck_in(otherwise, env)?);
+            if x != y {
+                return Err(TypeError::Mismatch("?:".into(), x, y))
+            }
+            x
+        }
+        Ast::Assign(name, value) => {
+            let kind = typecheck_in(value, env)?;
+            env.insert(name.clone(), kind);
+            kind
+        }
+        // an empty sequence evaluates to 0
+        Ast::Seq(statements) => {
+            let mut kind = Int;
+            for statement in statements {
+                kind = typecheck_in(statement, env)?;
+            }
+            kind
+        }
+        Ast::Let { name, value, body } => {
+            let kind = typecheck_in(value, env)?;
+            scoped(env, name, kind, |env| typecheck_in(body, env))?
+        }
+    };
+    Ok(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_with, ParseOptions};
+    use NumberKind::{Float, Int};
+
+    fn typecheck_str(input: &str) -> Result<NumberKind, TypeError> {
+        typecheck(&parse(input).unwrap())
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(typecheck_str("1 + 2"), Ok(Int));
+        assert_eq!(typecheck_str("1.0 + 2.0"), Ok(Float));
+        assert_eq!(typecheck_str("1 + 2.0"), Err(TypeError::Mismatch("+".into(), Int, Float)));
+        assert_eq!(typecheck_str("7 / 2"), Ok(Float));
+        assert_eq!(typecheck_str("7.0 / 2.0"), Ok(Float));
+        assert_eq!(typecheck_str("7 // 2"), Ok(Int));
+        assert_eq!(typecheck_str("7 / 2.0"), Err(TypeError::Mismatch("/".into(), Int, Float)));
+        assert_eq!(typecheck_str("1.5 < 2.5"), Ok(Int));
+        assert_eq!(typecheck_str("-3! ^ 2"), Ok(Int));
+        assert_eq!(typecheck_str("sqrt 2.0 * pi"), Ok(Float));
+        assert_eq!(typecheck_str("max(1, 2, 3)"), Ok(Int));
+        assert_eq!(typecheck_str("log(8.0, 2.0)"), Ok(Float));
+        assert_eq!(typecheck_str("1 < 2 ? 1.0 : 2.0"), Ok(Float));
+        assert_eq!(typecheck_str("6 & 3 << 1"), Ok(Int));
+        assert_eq!(typecheck_str("x = 2.0; y = x * x; y"), Ok(Float));
+        let shadowed = typecheck_str("x = 1; (let x = 2.0 in x) + x");
+        assert_eq!(shadowed, Err(TypeError::Mismatch("+".into(), Float, Int)));
+
+        let options = ParseOptions { percent: true, ..ParseOptions::default() };
+        assert_eq!(typecheck(&parse_with("50% + 0.5", &options).unwrap()), Ok(Float));
+        assert_eq!(typecheck(&parse_with("12.5%", &options).unwrap()), Ok(Float));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(typecheck_str("(1 + 2) * 3.0"), Err(TypeError::Mismatch("*".into(), Int, Float)));
+        assert_eq!(typecheck_str("sqrt 4"), Err(TypeError::Unsupported("sqrt".into(), Int)));
+        assert_eq!(typecheck_str("2.0!"), Err(TypeError::Unsupported("!".into(), Float)));
+        assert_eq!(typecheck_str("1.0 & 2.0"), Err(TypeError::Unsupported("&".into(), Float)));
+        assert_eq!(typecheck_str("max(1, 2.0)"), Err(TypeError::Mismatch("max".into(), Int, Float)));
+        assert_eq!(typecheck_str("log 8"), Err(TypeError::Unsupported("log".into(), Int)));
+        assert_eq!(typecheck_str("1 ? 2 : 3.0"), Err(TypeError::Mismatch("?:".into(), Int, Float)));
+        assert_eq!(typecheck_str("x + 1"), Err(TypeError::UnboundVariable("x".into())));
+        assert_eq!(typecheck_str("max()"), Err(TypeError::UnknownOperator("max".into())));
+    }
+
+    #[test]
+    fn conversions() {
+        let mut options = ParseOptions::default();
+        options.functions.extend(["int".into(), "float".into(), "round".into()]);
+        let typecheck_str = |input| typecheck(&parse_with(input, &options).unwrap());
+        assert_eq!(typecheck_str("float 1 + 2.0"), Ok(Float));
+        assert_eq!(typecheck_str("int(7 / 2) + 1"), Ok(Int));
+        assert_eq!(typecheck_str("1 + int 2.0"), Ok(Int));
+        assert_eq!(typecheck_str("sqrt float(4)"), Ok(Float));
+        assert_eq!(typecheck_str("round 2.5"), Err(TypeError::UnknownOperator("round".into())));
+    }
+}